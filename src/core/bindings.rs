@@ -0,0 +1,320 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/*
+ * What the player can ask for, independently of the key pressed
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+  MoveForward,
+  MoveBackward,
+  MoveLeft,
+  MoveRight,
+  MoveUp,
+  MoveDown,
+  LookUp,
+  LookDown,
+  LookLeft,
+  LookRight,
+  ZoomIn,
+  ZoomOut,
+  RotateModel,
+  RotateModelBack,
+  CycleRenderMode,
+  ToggleWireframe,
+  SwitchController,
+  ToggleCameraPath,
+  FrameAll,
+  FrameNext,
+  MoveLight,
+  Pause,
+  ToggleHud,
+  Quit
+}
+
+const ACTION_NAMES: [(Action, &str); 24] = [
+  (Action::MoveForward, "move_forward"),
+  (Action::MoveBackward, "move_backward"),
+  (Action::MoveLeft, "move_left"),
+  (Action::MoveRight, "move_right"),
+  (Action::MoveUp, "move_up"),
+  (Action::MoveDown, "move_down"),
+  (Action::LookUp, "look_up"),
+  (Action::LookDown, "look_down"),
+  (Action::LookLeft, "look_left"),
+  (Action::LookRight, "look_right"),
+  (Action::ZoomIn, "zoom_in"),
+  (Action::ZoomOut, "zoom_out"),
+  (Action::RotateModel, "rotate_model"),
+  (Action::RotateModelBack, "rotate_model_back"),
+  (Action::CycleRenderMode, "cycle_render_mode"),
+  (Action::ToggleWireframe, "toggle_wireframe"),
+  (Action::SwitchController, "switch_controller"),
+  (Action::ToggleCameraPath, "toggle_camera_path"),
+  (Action::FrameAll, "frame_all"),
+  (Action::FrameNext, "frame_next"),
+  (Action::MoveLight, "move_light"),
+  (Action::Pause, "pause"),
+  (Action::ToggleHud, "toggle_hud"),
+  (Action::Quit, "quit"),
+];
+
+impl Action {
+  pub fn all () -> impl Iterator<Item = Action> {
+    ACTION_NAMES.iter().map(|&(action, _)| action)
+  }
+
+  /*
+   * Applied on every frame while its key is held, the others once per key press
+   */
+  pub fn is_continuous (self) -> bool {
+    matches!(self,
+      Action::MoveForward | Action::MoveBackward | Action::MoveLeft | Action::MoveRight | Action::MoveUp | Action::MoveDown
+      | Action::LookUp | Action::LookDown | Action::LookLeft | Action::LookRight | Action::MoveLight)
+  }
+
+  /*
+   * Applied again on the key repeats
+   */
+  pub fn repeats (self) -> bool {
+    matches!(self, Action::ZoomIn | Action::ZoomOut | Action::RotateModel | Action::RotateModelBack)
+  }
+}
+
+impl fmt::Display for Action {
+  fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = ACTION_NAMES.iter().find(|(action, _)| action == self).map_or("", |(_, name)| name);
+    write!(f, "{}", name)
+  }
+}
+
+impl FromStr for Action {
+  type Err = String;
+
+  fn from_str (s: &str) -> Result<Self, Self::Err> {
+    ACTION_NAMES.iter()
+      .find(|(_, name)| *name == s)
+      .map(|&(action, _)| action)
+      .ok_or_else(|| format!("Unknown action '{}'", s))
+  }
+}
+
+/*
+ * Key with its modifiers, the shift is part of the char for the printable keys
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+  pub code: KeyCode,
+  pub modifiers: KeyModifiers
+}
+
+impl Key {
+  pub fn new (code: KeyCode, modifiers: KeyModifiers) -> Key {
+    match code {
+      // some terminals (kitty protocol) give the unshifted letter with the shift
+      KeyCode::Char(char) => Key {
+        code: KeyCode::Char(if modifiers.contains(KeyModifiers::SHIFT) { char.to_ascii_uppercase() } else { char }),
+        modifiers: modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT)
+      },
+      _ => Key { code, modifiers: modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT) }
+    }
+  }
+
+  pub fn from_event (event: &KeyEvent) -> Key {
+    Key::new(event.code, event.modifiers)
+  }
+}
+
+const KEY_NAMES: [(KeyCode, &str); 14] = [
+  (KeyCode::Char(' '), "space"),
+  (KeyCode::Up, "up"),
+  (KeyCode::Down, "down"),
+  (KeyCode::Left, "left"),
+  (KeyCode::Right, "right"),
+  (KeyCode::Esc, "esc"),
+  (KeyCode::Enter, "enter"),
+  (KeyCode::Tab, "tab"),
+  (KeyCode::Backspace, "backspace"),
+  (KeyCode::Delete, "delete"),
+  (KeyCode::Home, "home"),
+  (KeyCode::End, "end"),
+  (KeyCode::PageUp, "pageup"),
+  (KeyCode::PageDown, "pagedown"),
+];
+
+/*
+ * "ctrl+space", "shift+up", "alt+x", "F", "f5"...
+ */
+impl FromStr for Key {
+  type Err = String;
+
+  fn from_str (s: &str) -> Result<Self, Self::Err> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = s;
+    // the last '+' separates the key, unless the key is '+' itself
+    while let Some((modifier, rest)) = name.split_once('+').filter(|(_, rest)| !rest.is_empty()) {
+      modifiers |= match modifier.to_lowercase().as_str() {
+        "ctrl" | "control" => KeyModifiers::CONTROL,
+        "alt" => KeyModifiers::ALT,
+        "shift" => KeyModifiers::SHIFT,
+        _ => return Err(format!("Unknown modifier '{}' in the key '{}'", modifier, s))
+      };
+      name = rest;
+    }
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+      (Some(char), None) => KeyCode::Char(char),
+      _ => {
+        let lowercase = name.to_lowercase();
+        match KEY_NAMES.iter().find(|(_, key_name)| *key_name == lowercase) {
+          Some(&(code, _)) => code,
+          None => match lowercase.strip_prefix('f').and_then(|number| number.parse::<u8>().ok()) {
+            Some(number) if (1..=12).contains(&number) => KeyCode::F(number),
+            _ => return Err(format!("Unknown key '{}'", s))
+          }
+        }
+      }
+    };
+    Ok(Key::new(code, modifiers))
+  }
+}
+
+impl fmt::Display for Key {
+  fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (modifier, name) in [(KeyModifiers::CONTROL, "ctrl+"), (KeyModifiers::ALT, "alt+"), (KeyModifiers::SHIFT, "shift+")] {
+      if self.modifiers.contains(modifier) {
+        write!(f, "{}", name)?;
+      }
+    }
+    match KEY_NAMES.iter().find(|(code, _)| *code == self.code) {
+      Some((_, name)) => write!(f, "{}", name),
+      None => match self.code {
+        KeyCode::Char(char) => write!(f, "{}", char),
+        KeyCode::F(number) => write!(f, "f{}", number),
+        code => write!(f, "{:?}", code)
+      }
+    }
+  }
+}
+
+/*
+ * Keyboard layout giving the default keys
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Layout {
+  Qwerty,
+  #[default]
+  Azerty
+}
+
+impl FromStr for Layout {
+  type Err = String;
+
+  fn from_str (s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "qwerty" => Ok(Layout::Qwerty),
+      "azerty" => Ok(Layout::Azerty),
+      _ => Err(format!("Unknown keyboard layout '{}', expected qwerty or azerty", s))
+    }
+  }
+}
+
+/*
+ * Action triggered by each key
+ */
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+  keys: HashMap<Key, Action>
+}
+
+impl Default for KeyBindings {
+  fn default () -> Self {
+    KeyBindings::profile(Layout::default())
+  }
+}
+
+impl KeyBindings {
+  pub fn profile (layout: Layout) -> KeyBindings {
+    let (forward, left, backward, right) = match layout {
+      Layout::Qwerty => ("w", "a", "s", "d"),
+      Layout::Azerty => ("z", "q", "s", "d")
+    };
+    let defaults = [
+      (Action::MoveForward, forward),
+      (Action::MoveBackward, backward),
+      (Action::MoveLeft, left),
+      (Action::MoveRight, right),
+      (Action::MoveUp, "space"),
+      (Action::MoveDown, "ctrl+space"),
+      (Action::LookUp, "up"),
+      (Action::LookDown, "down"),
+      (Action::LookLeft, "left"),
+      (Action::LookRight, "right"),
+      (Action::ZoomIn, "+"),
+      (Action::ZoomIn, "="),
+      (Action::ZoomOut, "-"),
+      (Action::RotateModel, "r"),
+      (Action::RotateModelBack, "R"),
+      (Action::CycleRenderMode, "m"),
+      (Action::ToggleWireframe, "x"),
+      (Action::SwitchController, "c"),
+      (Action::ToggleCameraPath, "k"),
+      (Action::FrameAll, "f"),
+      (Action::FrameNext, "F"),
+      (Action::MoveLight, "t"),
+      (Action::Pause, "p"),
+      (Action::ToggleHud, "h"),
+      (Action::Quit, "esc"),
+      (Action::Quit, "ctrl+c"),
+    ];
+    let mut bindings = KeyBindings { keys: HashMap::new() };
+    for (action, key) in defaults {
+      bindings.bind(key.parse().expect("Invalid default key"), action);
+    }
+    bindings
+  }
+
+  pub fn bind (&mut self, key: Key, action: Action) {
+    self.keys.insert(key, action);
+  }
+
+  /*
+   * Remove every key of the action
+   */
+  pub fn unbind (&mut self, action: Action) {
+    self.keys.retain(|_, bound| *bound != action);
+  }
+
+  pub fn action (&self, event: &KeyEvent) -> Option<Action> {
+    self.action_of(&Key::from_event(event))
+  }
+
+  pub fn action_of (&self, key: &Key) -> Option<Action> {
+    self.keys.get(key).copied()
+  }
+
+  /*
+   * Keys of the action, sorted by name
+   */
+  pub fn keys (&self, action: Action) -> Vec<Key> {
+    let mut keys: Vec<Key> = self.keys.iter()
+      .filter(|(_, bound)| **bound == action)
+      .map(|(key, _)| *key)
+      .collect();
+    keys.sort_by_key(|key| key.to_string());
+    keys
+  }
+}
+
+impl fmt::Display for KeyBindings {
+  fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let bindings: Vec<String> = Action::all()
+      .map(|action| {
+        let keys: Vec<String> = self.keys(action).iter().map(Key::to_string).collect();
+        format!("{}={}", action, keys.join("|"))
+      })
+      .collect();
+    write!(f, "{}", bindings.join(", "))
+  }
+}
@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use super::{engine::Camera, math::{quaternion::Quat, vector::Vec3}, projection::Projection};
+
+/*
+ * Curve followed by the position between the keyframes
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Interpolation {
+  Linear,
+  #[default]
+  CatmullRom,
+  Bezier
+}
+
+impl FromStr for Interpolation {
+  type Err = String;
+
+  fn from_str (s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "linear" => Ok(Interpolation::Linear),
+      "catmull_rom" => Ok(Interpolation::CatmullRom),
+      "bezier" => Ok(Interpolation::Bezier),
+      _ => Err(format!("Unknown interpolation '{}', expected linear, catmull_rom or bezier", s))
+    }
+  }
+}
+
+/*
+ * Camera pose at `time` seconds, the field of view is kept when unset.
+ * The Bezier handles surrounding the position are derived from the neighbours when unset.
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct Keyframe {
+  pub time: f32,
+  pub position: Vec3,
+  pub orientation: Quat,
+  pub fov: Option<f32>,
+  pub handle_in: Option<Vec3>,
+  pub handle_out: Option<Vec3>
+}
+
+impl Keyframe {
+  pub fn new (time: f32, position: Vec3, yaw: f32, pitch: f32) -> Keyframe {
+    Keyframe { time, position, orientation: Quat::from_yaw_pitch(yaw, pitch), fov: None, handle_in: None, handle_out: None }
+  }
+
+  pub fn looking_at (time: f32, position: Vec3, target: Vec3) -> Keyframe {
+    let mut cam = Camera::new(position, 0.0, 0.0, Projection::default());
+    cam.look_at(target);
+    Keyframe::new(time, position, cam.yaw, cam.pitch)
+  }
+}
+
+/*
+ * Keyframes sorted by time, a looping path restarts at its end.
+ * A looping path ending where it starts is closed: the curve goes on smoothly through the first keyframe.
+ */
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+  keyframes: Vec<Keyframe>,
+  pub interpolation: Interpolation,
+  pub looping: bool
+}
+
+impl CameraPath {
+  pub fn new (mut keyframes: Vec<Keyframe>, interpolation: Interpolation, looping: bool) -> Result<CameraPath, String> {
+    if keyframes.is_empty() {
+      return Err(String::from("A camera path needs at least one keyframe"));
+    }
+    if keyframes.iter().any(|keyframe| !keyframe.time.is_finite() || keyframe.time < 0.0) {
+      return Err(String::from("The keyframe times must be positive"));
+    }
+    for fov in keyframes.iter().filter_map(|keyframe| keyframe.fov) {
+      Projection::perspective(fov)?;
+    }
+    keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(CameraPath { keyframes, interpolation, looping })
+  }
+
+  /*
+   * Closed path turning around `center` in `duration` seconds while looking at it
+   */
+  pub fn circle (center: Vec3, radius: f32, height: f32, duration: f32) -> CameraPath {
+    const STEPS: usize = 8;
+    let keyframes = (0..=STEPS).map(|step| {
+      let angle = step as f32 / STEPS as f32 * std::f32::consts::TAU;
+      let position = center + Vec3::new(radius * angle.cos(), height, radius * angle.sin());
+      Keyframe::looking_at(step as f32 / STEPS as f32 * duration, position, center)
+    }).collect();
+    CameraPath { keyframes, interpolation: Interpolation::CatmullRom, looping: true }
+  }
+
+  pub fn keyframes (&self) -> &[Keyframe] {
+    &self.keyframes
+  }
+
+  pub fn duration (&self) -> f32 {
+    self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+  }
+
+  fn is_closed (&self) -> bool {
+    let (first, last) = (self.keyframes[0], self.keyframes[self.keyframes.len() - 1]);
+    self.looping && self.keyframes.len() > 2 && (last.position - first.position).length2() < 1e-6
+  }
+
+  fn position_at (&self, index: isize) -> Vec3 {
+    let count = self.keyframes.len() as isize;
+    let index = if self.is_closed() {
+      // the last keyframe is the first one
+      index.rem_euclid(count - 1)
+    } else {
+      index.clamp(0, count - 1)
+    };
+    self.keyframes[index as usize].position
+  }
+
+  /*
+   * Position, orientation and field of view at `time` seconds
+   */
+  pub fn sample (&self, time: f32) -> (Vec3, Quat, Option<f32>) {
+    let duration = self.duration();
+    let time = if self.looping && duration > 0.0 { time.rem_euclid(duration) } else { time };
+    let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+    if next == 0 || next == self.keyframes.len() {
+      let keyframe = self.keyframes[next.saturating_sub(1)];
+      return (keyframe.position, keyframe.orientation, self.fov_until(next.saturating_sub(1)));
+    }
+    let (a, b) = (self.keyframes[next - 1], self.keyframes[next]);
+    let t = if b.time > a.time { (time - a.time) / (b.time - a.time) } else { 1.0 };
+
+    let i = next as isize - 1;
+    let (p0, p1, p2, p3) = (self.position_at(i - 1), a.position, b.position, self.position_at(i + 2));
+    let position = match self.interpolation {
+      Interpolation::Linear => p1 + t * (p2 - p1),
+      Interpolation::CatmullRom => 0.5 * (
+        2.0 * p1
+        + t * (p2 - p0)
+        + t * t * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3)
+        + t * t * t * (3.0 * p1 - p0 - 3.0 * p2 + p3)
+      ),
+      Interpolation::Bezier => {
+        let c1 = a.handle_out.unwrap_or(p1 + (1.0 / 6.0) * (p2 - p0));
+        let c2 = b.handle_in.unwrap_or(p2 - (1.0 / 6.0) * (p3 - p1));
+        let s = 1.0 - t;
+        s * s * s * p1 + 3.0 * s * s * t * c1 + 3.0 * s * t * t * c2 + t * t * t * p2
+      }
+    };
+    let fov = match (self.fov_until(next - 1), self.fov_until(next)) {
+      (Some(fov_a), Some(fov_b)) => Some(fov_a + t * (fov_b - fov_a)),
+      (fov_a, fov_b) => fov_a.or(fov_b)
+    };
+    (position, a.orientation.slerp(b.orientation, t), fov)
+  }
+
+  // last field of view given up to the keyframe `index`
+  fn fov_until (&self, index: usize) -> Option<f32> {
+    self.keyframes[..=index].iter().rev().find_map(|keyframe| keyframe.fov)
+  }
+
+  /*
+   * Move the camera to its pose at `time` seconds, the field of view only changes a perspective
+   */
+  pub fn apply (&self, cam: &mut Camera, time: f32) {
+    let (position, orientation, fov) = self.sample(time);
+    cam.position = position;
+    (cam.yaw, cam.pitch) = orientation.to_yaw_pitch();
+    if let (Some(fov), Projection::Perspective { .. }) = (fov, cam.projection) {
+      cam.projection = Projection::Perspective { fov };
+    }
+  }
+
+  /*
+   * True once a path without loop has been played until its end
+   */
+  pub fn is_finished (&self, time: f32) -> bool {
+    !self.looping && time >= self.duration()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keyframe_fov_is_checked () {
+    let path = |fov: f32| {
+      let mut keyframe = Keyframe::new(0.0, Vec3::new(0.0, 0.0, 0.0), 0.0, 0.0);
+      keyframe.fov = Some(fov);
+      CameraPath::new(vec![Keyframe::new(1.0, Vec3::new(1.0, 0.0, 0.0), 0.0, 0.0), keyframe], Interpolation::Linear, false)
+    };
+    assert!(path(60.0).is_ok());
+    for fov in [0.0, -10.0, 180.0, 200.0, f32::NAN] {
+      assert_eq!(path(fov).err(), Projection::perspective(fov).err(), "{}", fov);
+      assert!(path(fov).is_err());
+    }
+  }
+}
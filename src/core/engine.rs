@@ -1,48 +1,258 @@
-use std::{io::{self}, time::{Duration, Instant}};
-use crossterm::event::{poll, read, Event, KeyCode, KeyModifiers};
+use std::{io::{self, Write}, sync::Arc, thread, time::{Duration, Instant}};
+use crossterm::{cursor::MoveTo, event::poll, queue, style::{Color, Print, ResetColor, SetForegroundColor}, Command};
 
-use super::{math::{math::cross_prod, triangle::{Triangle2D, Triangle3D}, vector::{Vec2, Vec3}}, player::player_action};
-use crate::{core::math::math::{dot, line_plane_intersection}, tools::logger::Logger};
+use super::{hud::{FrameStats, Hud, HudSettings}, material::Material, math::{math::cross_prod, matrix::Mat4, triangle::{Triangle2D, Triangle3D}, vector::{Vec2, Vec3}}, mesh::{Mesh, Point}, profiler::Profiler, scene::Scene, player::{player_action, Controls, Player}, projection::Projection, raster::{line_char, rasterize_line, rasterize_triangle, Fragment, RasterVertex, Rect, Varying}, texture::{luminance, TextureFilter}, terminal::TerminalSession, timing::{FrameClock, TimingSettings}, tiles::{bin_triangles, FrameBand, TILE_HEIGHT}};
+use crate::{core::math::math::{dot, line_plane_intersection, line_plane_parameter}, log_trace, tools::logger::Logger};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColorMode {
+  Monochrome,
+  TrueColor
+}
+
+impl std::str::FromStr for ColorMode {
+  type Err = String;
+  fn from_str (value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "monochrome" => Ok(ColorMode::Monochrome),
+      "truecolor" => Ok(ColorMode::TrueColor),
+      _ => Err(format!("Unknown colour mode '{}' (monochrome, truecolor)", value))
+    }
+  }
+}
+
+impl std::fmt::Display for ColorMode {
+  fn fmt (&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ColorMode::Monochrome => write!(f, "monochrome"),
+      ColorMode::TrueColor => write!(f, "truecolor")
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RenderMode {
+  Filled,
+  Wireframe,
+  PointCloud,
+  HiddenLine
+}
+
+impl RenderMode {
+  pub fn next (self) -> RenderMode {
+    match self {
+      RenderMode::Filled => RenderMode::Wireframe,
+      RenderMode::Wireframe => RenderMode::PointCloud,
+      RenderMode::PointCloud => RenderMode::HiddenLine,
+      RenderMode::HiddenLine => RenderMode::Filled
+    }
+  }
+}
+
+impl std::str::FromStr for RenderMode {
+  type Err = String;
+  fn from_str (value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "filled" => Ok(RenderMode::Filled),
+      "wireframe" => Ok(RenderMode::Wireframe),
+      "points" => Ok(RenderMode::PointCloud),
+      "hidden_line" => Ok(RenderMode::HiddenLine),
+      _ => Err(format!("Unknown render mode '{}' (filled, wireframe, points, hidden_line)", value))
+    }
+  }
+}
+
+impl std::fmt::Display for RenderMode {
+  fn fmt (&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      RenderMode::Filled => write!(f, "filled"),
+      RenderMode::Wireframe => write!(f, "wireframe"),
+      RenderMode::PointCloud => write!(f, "points"),
+      RenderMode::HiddenLine => write!(f, "hidden_line")
+    }
+  }
+}
+
+// relative depth tolerance letting the edges of a surface win against the surface itself
+const HIDDEN_LINE_BIAS: f32 = 0.01;
+// shading chars from the darkest to the brightest
+pub const DEFAULT_SHADING_RAMP: &str = ".,;la#@";
+
+/*
+ * Triangle projected on the screen with what is needed to shade its fragments
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct ScreenTriangle<'m> {
+  pub vertices: [RasterVertex<Vec2>; 3],
+  // vertex colours, interpolated by the shader
+  pub colors: Option<[Vec3; 3]>,
+  pub intensity: f32,
+  pub material: Option<&'m Material>,
+  pub has_uv: bool
+}
+
+impl ScreenTriangle<'_> {
+  /*
+   * Colour of the surface without texture nor light, the vertex colours averaged
+   */
+  pub fn diffuse (&self) -> Vec3 {
+    match self.colors {
+      Some(colors) => self.material_diffuse() * ((1.0 / 3.0) * (colors[0] + colors[1] + colors[2])),
+      None => self.material_diffuse()
+    }
+  }
+
+  pub fn material_diffuse (&self) -> Vec3 {
+    self.material.map_or(Vec3::new(1.0, 1.0, 1.0), |material| material.diffuse)
+  }
+}
+
+/*
+ * World-space triangles of a node, reused while its mesh and world transform stay the same
+ * and the scene keeps its revision
+ */
+#[derive(Debug)]
+struct CachedInstance {
+  mesh: usize,
+  transform: Mat4,
+  triangles: Vec<Triangle3D>,
+  points: Vec<Point>
+}
 
 #[derive(Debug)]
 pub struct Engine<'a> {
   pub width: usize,
   pub height: usize,
-  pub pixel_ratio: usize,
+  // height divided by the width of a terminal cell
+  pub cell_aspect: f32,
   pub pixel_buffer_size: usize,
   pub pixel_buffer: Vec<char>,
-  pub logger: &'a Logger
+  pub depth_buffer: Vec<f32>,
+  pub color_buffer: Vec<Vec3>,
+  pub color_mode: ColorMode,
+  pub render_mode: RenderMode,
+  pub texture_filter: TextureFilter,
+  pub face_culling: bool,
+  pub threads: usize,
+  pub background: char,
+  pub background_color: Vec3,
+  pub controls: Controls,
+  // distances from the camera to the clipping planes, the far one can be infinite
+  pub z_near: f32,
+  pub z_far: f32,
+  pub shading_ramp: Vec<char>,
+  // the view is resized with the terminal, unless its size is given
+  pub follow_terminal: bool,
+  // projection of the camera placed by the engine when the scene has none
+  pub projection: Projection,
+  pub timing: TimingSettings,
+  pub hud: HudSettings,
+  // shared with the timers of the stages
+  pub profiler: Arc<Profiler>,
+  // filled while the frame is rendered
+  pub stats: FrameStats,
+  pub logger: &'a Logger,
+  instance_cache: Vec<Option<CachedInstance>>,
+  // bounding sphere of each mesh of the scene
+  bounds_cache: Vec<(Vec3, f32)>,
+  // revision of the scene the caches were filled from
+  cache_revision: Option<u64>
 }
 
-impl ToString for Engine<'_> {
-    fn to_string(&self) -> String {
-        format!("Engine [width={}, height={}, pixel_buffer_size={}]", self.width, self.height, self.pixel_buffer_size)
+impl std::fmt::Display for Engine<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Engine [width={}, height={}, pixel_buffer_size={}, threads={}]", self.width, self.height, self.pixel_buffer_size, self.threads)
     }
 }
 
 impl Engine<'_> {
-  pub fn new (width: usize, height: usize, logger: &Logger) -> Engine {
+  pub fn new (width: usize, height: usize, logger: &Logger) -> Engine<'_> {
     if width < 1 || height < 1 {
       panic!("The width or height must be upper than 0");
     }
     Engine {
-      width: width.clone(),
-      height: height.clone(),
-      pixel_ratio: 29/13,
+      width,
+      height,
+      cell_aspect: 29.0 / 13.0,
       pixel_buffer_size: width * height,
       pixel_buffer: vec![' ' ; width * height],
-      logger
+      depth_buffer: vec![0.0 ; width * height],
+      color_buffer: vec![Vec3::new(1.0, 1.0, 1.0) ; width * height],
+      color_mode: ColorMode::Monochrome,
+      render_mode: RenderMode::Filled,
+      texture_filter: TextureFilter::Bilinear,
+      face_culling: false,
+      threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+      background: ' ',
+      background_color: Vec3::new(1.0, 1.0, 1.0),
+      controls: Controls::default(),
+      z_near: 0.1,
+      z_far: f32::INFINITY,
+      shading_ramp: DEFAULT_SHADING_RAMP.chars().collect(),
+      follow_terminal: true,
+      projection: Projection::default(),
+      timing: TimingSettings::default(),
+      hud: HudSettings::default(),
+      profiler: Arc::new(Profiler::default()),
+      stats: FrameStats::default(),
+      logger,
+      instance_cache: vec![],
+      bounds_cache: vec![],
+      cache_revision: None
     }
   }
 
+  /*
+   * Print the frame from the top left corner of the terminal
+   */
   pub fn draw (&self) {
-    println!("{}", String::from_iter(&self.pixel_buffer));
+    let frame = match self.color_mode {
+      ColorMode::Monochrome => String::from_iter(&self.pixel_buffer),
+      ColorMode::TrueColor => self.colored_frame()
+    };
+    let mut out = io::stdout().lock();
+    let _ = queue!(out, MoveTo(0, 0), Print(frame));
+    let _ = out.flush();
   }
 
-  pub fn clear (&mut self, char: char) {
-    for idx in 0..self.pixel_buffer_size {
-      (self.pixel_buffer)[idx] = char;
+  /*
+   * Frame with an ANSI colour sequence each time the colour changes
+   */
+  fn colored_frame (&self) -> String {
+    let mut frame = String::with_capacity(self.pixel_buffer_size * 2);
+    let mut current: Option<(u8, u8, u8)> = None;
+    for (char, color) in self.pixel_buffer.iter().zip(&self.color_buffer) {
+      let to_u8 = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+      let rgb = (to_u8(color.x), to_u8(color.y), to_u8(color.z));
+      if current != Some(rgb) {
+        let _ = SetForegroundColor(Color::Rgb { r: rgb.0, g: rgb.1, b: rgb.2 }).write_ansi(&mut frame);
+        current = Some(rgb);
+      }
+      frame.push(*char);
     }
+    let _ = ResetColor.write_ansi(&mut frame);
+    frame
+  }
+
+  /*
+   * Reallocate the buffers for a view of the new size, at least one cell
+   */
+  pub fn resize (&mut self, width: usize, height: usize) {
+    let (width, height) = (width.max(1), height.max(1));
+    self.width = width;
+    self.height = height;
+    self.pixel_buffer_size = width * height;
+    self.pixel_buffer = vec![self.background ; width * height];
+    self.depth_buffer = vec![0.0 ; width * height];
+    self.color_buffer = vec![self.background_color ; width * height];
+  }
+
+  pub fn clear (&mut self, char: char) {
+    // the frame is presented after its rendering, its times are shown with the next one
+    self.stats = FrameStats { times: self.profiler.last_frame(), ..FrameStats::default() };
+    self.pixel_buffer.fill(char);
+    self.depth_buffer.fill(0.0);
+    self.color_buffer.fill(self.background_color);
   }
 
   pub fn put_pixel (&mut self, pixel: &Vec2, char: char) {
@@ -56,94 +266,255 @@ impl Engine<'_> {
     }
   }
 
+  /*
+   * Write a line of text from the cell (x, y), cut at the right border
+   */
+  pub fn put_text (&mut self, x: usize, y: usize, text: &str) {
+    if y >= self.height {
+      return;
+    }
+    for (column, char) in (x..self.width).zip(text.chars()) {
+      let index = y * self.width + column;
+      self.pixel_buffer[index] = char;
+      self.depth_buffer[index] = f32::INFINITY;
+      self.color_buffer[index] = Vec3::new(1.0, 1.0, 1.0);
+    }
+  }
+
   pub fn put_triangle (&mut self, tri: &Triangle2D, char: char) {
-    fn eq (p:Vec2, a:Vec2, b:Vec2) -> isize {
-      ((a.x - p.x) * (b.y - p.y) - (a.y - p.y) * (b.x - p.x)) as isize
-    }
-    let width = self.width.try_into().unwrap();
-    let height = self.height.try_into().unwrap();
-    let x_set = vec![tri.v1.x as isize, tri.v2.x as isize, tri.v3.x as isize];
-    let xmin:&isize = x_set.iter().min().unwrap();
-    let xmax:&isize = x_set.iter().max().unwrap();
-    let y_set = vec![tri.v1.y as isize, tri.v2.y as isize, tri.v3.y as isize];
-    let ymin:&isize = y_set.iter().min().unwrap();
-    let ymax:&isize = y_set.iter().max().unwrap();
-    for y in *ymin..(*ymax+1.0 as isize) {
-      if 0 <= y && y < height {
-        for x in *xmin..(*xmax+1.0 as isize) {
-          if 0 <= x && x < width {
-            let pos = Vec2::new(x as f32, y as f32);
-            let w1:isize = eq(pos, tri.v3, tri.v1) as isize;
-            let w2:isize = eq(pos, tri.v1, tri.v2) as isize;
-            let w3:isize = eq(pos, tri.v2, tri.v3) as isize;
-            if (w1 >= 0 && w2 >= 0 && w3 >= 0) || (-w1 >= 0 && -w2 >= 0 && -w3 >= 0) {
-              self.put_pixel(&pos, char);
+    let vertices = [
+      RasterVertex::new(tri.v1, 1.0, ()),
+      RasterVertex::new(tri.v2, 1.0, ()),
+      RasterVertex::new(tri.v3, 1.0, ()),
+    ];
+    let width = self.width;
+    let pixel_buffer = &mut self.pixel_buffer;
+    rasterize_triangle(&vertices, &Rect::screen(self.width, self.height), |fragment| {
+      pixel_buffer[fragment.y * width + fragment.x] = char;
+    });
+  }
+
+  /*
+   * Whole frame as a single band
+   */
+  pub fn frame (&mut self) -> FrameBand<'_> {
+    FrameBand {
+      y0: 0,
+      width: self.width,
+      pixels: &mut self.pixel_buffer,
+      depths: &mut self.depth_buffer,
+      colors: &mut self.color_buffer
+    }
+  }
+
+  /*
+   * Rasterize a triangle with a depth test, `shader` gives the char and colour of each visible fragment
+   */
+  pub fn put_shaded_triangle<V: Varying, S: Fn(&Fragment<V>) -> Option<(char, Vec3)>> (&mut self, vertices: &[RasterVertex<V>; 3], shader: S) {
+    let screen = Rect::screen(self.width, self.height);
+    self.frame().put(vertices, &screen, shader);
+  }
+
+  /*
+   * Rasterize the triangles in order. With several threads, the screen is split in tiles
+   * rasterized in parallel; each cell still receives its triangles in the same order,
+   * so the frame is identical to the single-threaded one.
+   */
+  pub fn put_screen_triangles<S> (&mut self, triangles: &[ScreenTriangle], shader: S)
+  where S: Fn(&ScreenTriangle, &Fragment<Vec2>) -> Option<(char, Vec3)> + Sync {
+    if self.threads <= 1 {
+      let screen = Rect::screen(self.width, self.height);
+      let mut frame = self.frame();
+      for triangle in triangles {
+        frame.put(&triangle.vertices, &screen, |fragment| shader(triangle, fragment));
+      }
+      return;
+    }
+
+    let tile_rows = bin_triangles(triangles.iter().map(|triangle| &triangle.vertices), self.width, self.height);
+    let band_size = self.width * TILE_HEIGHT;
+    let bands = self.pixel_buffer.chunks_mut(band_size)
+      .zip(self.depth_buffer.chunks_mut(band_size))
+      .zip(self.color_buffer.chunks_mut(band_size))
+      .enumerate()
+      .map(|(row, ((pixels, depths), colors))| FrameBand { y0: row * TILE_HEIGHT, width: self.width, pixels, depths, colors });
+
+    // bands are dealt in turn to the workers to balance the load
+    let threads = self.threads.min(tile_rows.len()).max(1);
+    let mut jobs: Vec<Vec<_>> = (0..threads).map(|_| vec![]).collect();
+    for (row, job) in bands.zip(&tile_rows).enumerate() {
+      jobs[row % threads].push(job);
+    }
+    let shader = &shader;
+    thread::scope(|scope| {
+      for job in jobs {
+        scope.spawn(move || {
+          for (mut band, tiles) in job {
+            for tile in tiles {
+              for &index in &tile.triangles {
+                let triangle = &triangles[index];
+                band.put(&triangle.vertices, &tile.bounds, |fragment| shader(triangle, fragment));
+              }
             }
           }
+        });
+      }
+    });
+  }
+
+  /*
+   * Draw the edges of the triangles, hidden by the depth buffer if `depth_test` is set
+   */
+  pub fn put_edges (&mut self, triangles: &[ScreenTriangle], depth_test: bool) {
+    let screen = Rect::screen(self.width, self.height);
+    let frame = self.frame();
+    for triangle in triangles {
+      let color = triangle.diffuse();
+      let ends = triangle.vertices.map(|vertex| vertex.with_varying(()));
+      for (a, b) in [(ends[0], ends[1]), (ends[1], ends[2]), (ends[2], ends[0])] {
+        let char = line_char(a.position, b.position);
+        rasterize_line(&a, &b, &screen, |x, y, depth| {
+          let index = y * frame.width + x;
+          if depth_test && depth < frame.depths[index] * (1.0 - HIDDEN_LINE_BIAS) {
+            return;
+          }
+          frame.pixels[index] = char;
+          frame.colors[index] = color;
+        });
+      }
+    }
+  }
+
+  /*
+   * Draw the vertices of the triangles as dots, the nearest one wins
+   */
+  pub fn put_points (&mut self, triangles: &[ScreenTriangle]) {
+    let (width, height) = (self.width as f32, self.height as f32);
+    let frame = self.frame();
+    for triangle in triangles {
+      let diffuse = triangle.material_diffuse();
+      for (i, vertex) in triangle.vertices.iter().enumerate() {
+        let color = match triangle.colors {
+          Some(colors) => diffuse * colors[i],
+          None => diffuse
+        };
+        let (x, y) = (vertex.position.x.floor(), vertex.position.y.floor());
+        if 0.0 <= x && x < width && 0.0 <= y && y < height {
+          let index = y as usize * frame.width + x as usize;
+          // 1/z rather than 1/w, which is constant in orthographic projection
+          let depth = 1.0 / vertex.z;
+          if depth > frame.depths[index] {
+            frame.depths[index] = depth;
+            frame.pixels[index] = '.';
+            frame.colors[index] = color;
+          }
         }
       }
     }
   }
 
+  /*
+   * Cut the triangle by the near plane, and by the far plane when it is finite
+   */
   pub fn clip (&mut self, triangle: Triangle3D, cam: &Camera, normal_plane: Vec3) -> Vec<Triangle3D> {
-    fn in_z (normal_plane: Vec3, normal_point: Vec3, triangle: Triangle3D) -> (Vec<Vec3>, Vec<Vec3>, bool) {
-      let mut out: Vec<Vec3> = vec![];
-      let mut in_: Vec<Vec3> = vec![];
-      let vert1 = dot(normal_point - triangle.v1, normal_plane);
-      let vert2 = dot(normal_point - triangle.v2, normal_plane);
-      let vert3 = dot(normal_point - triangle.v3, normal_plane);
+    let mut planes = vec![(cam.position + self.z_near * normal_plane, normal_plane)];
+    if self.z_far.is_finite() {
+      planes.push((cam.position + self.z_far * normal_plane, -1.0 * normal_plane));
+    }
+    let is_cut = planes.iter().any(|&(point, normal)| {
+      [triangle.v1, triangle.v2, triangle.v3].iter().any(|&vertex| dot(point - vertex, normal) > 0.0)
+    });
+    if !is_cut {
+      return vec![triangle];
+    }
+    self.stats.clipped += 1;
+    planes.iter().fold(vec![triangle], |triangles, &(point, normal)| {
+      triangles.into_iter().flat_map(|triangle| Engine::clip_by_plane(triangle, point, normal)).collect()
+    })
+  }
+
+  /*
+   * Keep the part of the triangle in front of the plane
+   */
+  fn clip_by_plane (triangle: Triangle3D, plane_point: Vec3, normal_plane: Vec3) -> Vec<Triangle3D> {
+    // vertex position with its texture coordinate and colour
+    type ClipVertex = (Vec3, Vec2, Vec3);
+    fn in_z (normal_plane: Vec3, normal_point: Vec3, vertices: [ClipVertex; 3]) -> (Vec<ClipVertex>, Vec<ClipVertex>, bool) {
+      let mut out: Vec<ClipVertex> = vec![];
+      let mut in_: Vec<ClipVertex> = vec![];
+      let vert1 = dot(normal_point - vertices[0].0, normal_plane);
+      let vert2 = dot(normal_point - vertices[1].0, normal_plane);
+      let vert3 = dot(normal_point - vertices[2].0, normal_plane);
       if vert1 > 0.0 {
-        out.push(triangle.v1)
+        out.push(vertices[0])
       } else {
-        in_.push(triangle.v1)
+        in_.push(vertices[0])
       }
       if vert2 > 0.0 {
-        out.push(triangle.v2)
+        out.push(vertices[1])
       } else {
-        in_.push(triangle.v2)
+        in_.push(vertices[1])
       }
       if vert3 > 0.0 {
-        out.push(triangle.v3)
+        out.push(vertices[2])
       } else {
-        in_.push(triangle.v3)
+        in_.push(vertices[2])
       }
-      return (out, in_, vert1*vert3 > 0.0);
+      (out, in_, vert1*vert3 > 0.0)
     }
-    let z_near: Vec3 = cam.position + 0.1 * normal_plane;
-    let (out, in_, is_inverted) = in_z(normal_plane, z_near, triangle);
-    if out.len() == 0 {
+    let uv = triangle.uv.unwrap_or([Vec2::new(0.0, 0.0); 3]);
+    let colors = triangle.colors.unwrap_or([Vec3::new(1.0, 1.0, 1.0); 3]);
+    let vertices = [(triangle.v1, uv[0], colors[0]), (triangle.v2, uv[1], colors[1]), (triangle.v3, uv[2], colors[2])];
+    let (out, in_, is_inverted) = in_z(normal_plane, plane_point, vertices);
+    let intersection = |outside: ClipVertex, inside: ClipVertex| -> ClipVertex {
+      match line_plane_parameter(normal_plane, plane_point, outside.0, inside.0) {
+        Some(t) => (outside.0 + t * (inside.0 - outside.0), outside.1 + t * (inside.1 - outside.1), outside.2 + t * (inside.2 - outside.2)),
+        None => (line_plane_intersection(normal_plane, plane_point, outside.0, inside.0), outside.1, outside.2)
+      }
+    };
+    let new_triangle = |a: ClipVertex, b: ClipVertex, c: ClipVertex| -> Triangle3D {
+      let mut clipped = Triangle3D::new(a.0, b.0, c.0).with_normal(triangle.normal).with_material(triangle.material);
+      if triangle.uv.is_some() {
+        clipped = clipped.with_uv([a.1, b.1, c.1]);
+      }
+      if triangle.colors.is_some() {
+        clipped = clipped.with_colors([a.2, b.2, c.2]);
+      }
+      clipped
+    };
+    if out.is_empty() {
       return vec![triangle];
     } else if out.len() == 3 {
         return vec![];
     } else if out.len() == 1 {
-      let collision0 = line_plane_intersection(normal_plane, z_near, out[0], in_[0]);
-      let collision1 = line_plane_intersection(normal_plane, z_near, out[0], in_[1]);
+      let collision0 = intersection(out[0], in_[0]);
+      let collision1 = intersection(out[0], in_[1]);
       if is_inverted {
         return vec![
-          Triangle3D::new(collision1, in_[1], collision0),
-          Triangle3D::new(collision0, in_[1], in_[0]),
+          new_triangle(collision1, in_[1], collision0),
+          new_triangle(collision0, in_[1], in_[0]),
         ];
       } else {
         return vec![
-          Triangle3D::new(collision0, in_[0], collision1),
-          Triangle3D::new(collision1, in_[0], in_[1]),
+          new_triangle(collision0, in_[0], collision1),
+          new_triangle(collision1, in_[0], in_[1]),
         ];
       }
     } else if out.len() == 2 {
       if is_inverted {
         return vec![
-          Triangle3D::new(
-            line_plane_intersection(normal_plane, z_near, out[0], in_[0]),
+          new_triangle(
+            intersection(out[0], in_[0]),
             in_[0],
-            line_plane_intersection(normal_plane, z_near, out[1], in_[0]),
+            intersection(out[1], in_[0]),
           )
         ];
 
       } else {
         return vec![
-          Triangle3D::new(
-            line_plane_intersection(normal_plane, z_near, out[0], in_[0]),
-            line_plane_intersection(normal_plane, z_near, out[1], in_[0]),
+          new_triangle(
+            intersection(out[0], in_[0]),
+            intersection(out[1], in_[0]),
             in_[0]
           )
         ];
@@ -154,19 +525,193 @@ impl Engine<'_> {
 
   pub fn distance_triangle_camera (&self, triangle: Triangle3D, cam: &Camera) -> f32 {
     let position: Vec3 = ((triangle.v1+triangle.v2+triangle.v3)*(1.0/3.0)) - cam.position;
-    return position.length2();
+    position.length2()
+  }
+
+  /*
+   * Width divided by the height of the view, in world units
+   */
+  pub fn aspect_ratio (&self) -> f32 {
+    self.width as f32 / (self.height as f32 * self.cell_aspect)
+  }
+
+  /*
+   * Project a vertex expressed in the camera space on the screen
+   */
+  pub fn to_raster_vertex<V: Varying> (&self, vertex: Vec3, cam: &Camera, varying: V) -> RasterVertex<V> {
+    let position = cam.projection.project(vertex, self.aspect_ratio()).to_screen(self);
+    if cam.projection.is_perspective() {
+      RasterVertex::new(position, vertex.z, varying)
+    } else {
+      RasterVertex::orthographic(position, vertex.z, varying)
+    }
+  }
+
+  /*
+   * False when the sphere is entirely behind the near plane or outside the screen
+   */
+  pub fn is_sphere_visible (&self, center: Vec3, radius: f32, cam: &Camera) -> bool {
+    let view = (center - cam.position).rotation_y(cam.yaw).rotation_x(cam.pitch);
+    if view.z + radius < self.z_near || view.z - radius > self.z_far {
+      return false;
+    }
+    if !cam.projection.is_perspective() {
+      let (half_width, half_height) = cam.projection.half_extent(view.z, self.aspect_ratio());
+      return view.x.abs() - half_width <= radius && view.y.abs() - half_height <= radius;
+    }
+    // half width and half height of the view at a distance of 1
+    let (tan_x, tan_y) = cam.projection.half_extent(1.0, self.aspect_ratio());
+    (view.x.abs() - tan_x * view.z) / (1.0 + tan_x * tan_x).sqrt() <= radius
+      && (view.y.abs() - tan_y * view.z) / (1.0 + tan_y * tan_y).sqrt() <= radius
+  }
+
+  /*
+   * Forget the transformed vertices of the nodes and the bounds of the meshes when
+   * the scene is another one or its meshes changed, then compute the bounds again
+   */
+  fn update_scene_caches (&mut self, scene: &Scene) {
+    if self.cache_revision == Some(scene.revision()) {
+      return;
+    }
+    self.instance_cache.clear();
+    self.bounds_cache = scene.meshes().iter().map(Mesh::bounding_sphere).collect();
+    self.cache_revision = Some(scene.revision());
   }
 
-  pub fn put_mesh (&mut self, mut mesh: Vec<Triangle3D>, cam: &Camera, light_source: &LightSource) {
+  pub fn put_mesh (&mut self, mesh: &Mesh, cam: &Camera, light_source: &LightSource) {
+    self.put_meshes(&[(&mesh.triangles, mesh, None)], cam, std::slice::from_ref(light_source));
+  }
+
+  /*
+   * Draw every node of the scene with its world transform, the vertices of the meshes are left untouched.
+   * Nodes sharing a mesh are instances of it: each one is culled by its bounding sphere and keeps
+   * its transformed vertices from a frame to the next while it does not move and the scene
+   * keeps its revision.
+   */
+  pub fn put_scene (&mut self, scene: &Scene, cam: &Camera) {
+    let transform_scope = Profiler::scope(&self.profiler, "transform");
+    let world_transforms = scene.world_transforms();
+    self.update_scene_caches(scene);
+    let mut cache = std::mem::take(&mut self.instance_cache);
+    cache.truncate(scene.nodes.len());
+    cache.resize_with(scene.nodes.len(), || None);
+
+    let mut visible_nodes: Vec<usize> = vec![];
+    for (index, (node, transform)) in scene.nodes.iter().zip(&world_transforms).enumerate() {
+      let Some(mesh) = node.mesh else {
+        cache[index] = None;
+        continue;
+      };
+      let (center, radius) = self.bounds_cache[mesh];
+      let triangles = &scene.meshes()[mesh].triangles;
+      if !self.is_sphere_visible(transform.transform_point(center), radius * transform.max_scale(), cam) {
+        self.stats.submitted += triangles.len();
+        self.stats.culled += triangles.len();
+        continue;
+      }
+      let points = &scene.meshes()[mesh].points;
+      let is_cached = cache[index].as_ref().is_some_and(|cached| cached.mesh == mesh && cached.transform == *transform);
+      if !is_cached {
+        cache[index] = Some(CachedInstance {
+          mesh,
+          transform: *transform,
+          triangles: triangles.iter().map(|triangle| triangle.transform(transform)).collect(),
+          points: points.iter().map(|point| point.transform(transform)).collect()
+        });
+      }
+      visible_nodes.push(index);
+    }
+
+    let meshes: Vec<(&[Triangle3D], &Mesh, Option<&Material>)> = visible_nodes.iter()
+      .filter_map(|&index| cache[index].as_ref().map(|cached| {
+        let node = &scene.nodes[index];
+        (cached.triangles.as_slice(), &scene.meshes()[cached.mesh], node.material.as_ref())
+      }))
+      .collect();
+    drop(transform_scope);
+    self.put_meshes(&meshes, cam, &scene.lights);
+    let clouds: Vec<(&[Point], Option<&Material>)> = visible_nodes.iter()
+      .filter_map(|&index| cache[index].as_ref().map(|cached| (cached.points.as_slice(), scene.nodes[index].material.as_ref())))
+      .filter(|(points, _)| !points.is_empty())
+      .collect();
+    if !clouds.is_empty() {
+      self.put_clouds(&clouds, cam, &scene.lights);
+    }
+    self.instance_cache = cache;
+  }
+
+  /*
+   * Draw points already placed in the world as dots shaded by their colour, the colour of the
+   * material multiplying it. A point with a normal is lit, and culled when it faces away.
+   */
+  pub fn put_clouds (&mut self, clouds: &[(&[Point], Option<&Material>)], cam: &Camera, lights: &[LightSource]) {
+    let _raster_scope = Profiler::scope(&self.profiler, "raster");
+    let look_at: Vec3 = cam.get_look_at_direction();
+    let (width, height) = (self.width as f32, self.height as f32);
+    for &(points, material) in clouds {
+      let diffuse = material.map_or(Vec3::new(1.0, 1.0, 1.0), |material| material.diffuse);
+      for point in points {
+        let distance = dot(point.position - cam.position, look_at);
+        if distance < self.z_near || distance > self.z_far {
+          continue;
+        }
+        let intensity = match point.normal {
+          Some(normal) => {
+            let view_direction = match cam.projection {
+              Projection::Perspective { .. } => point.position - cam.position,
+              Projection::Orthographic { .. } => look_at
+            };
+            if self.face_culling && dot(normal, view_direction) >= 0.0 {
+              continue;
+            }
+            lights.iter().map(|light_source| light_source.diffuse_intensity(normal, point.position)).sum::<f32>().min(1.0)
+          },
+          None => 1.0
+        };
+        let view = (point.position - cam.position).rotation_y(cam.yaw).rotation_x(cam.pitch);
+        let vertex = self.to_raster_vertex(view, cam, ());
+        let (x, y) = (vertex.position.x.floor(), vertex.position.y.floor());
+        if !(0.0 <= x && x < width && 0.0 <= y && y < height) {
+          continue;
+        }
+        let index = y as usize * self.width + x as usize;
+        // same depth as the triangle fragments
+        let depth = 1.0 / vertex.z;
+        if depth > self.depth_buffer[index] {
+          let color = intensity * (diffuse * point.color.unwrap_or(Vec3::new(1.0, 1.0, 1.0)));
+          self.depth_buffer[index] = depth;
+          self.pixel_buffer[index] = shading_char(&self.shading_ramp, luminance(color));
+          self.color_buffer[index] = color;
+        }
+      }
+    }
+  }
+
+  /*
+   * Draw triangles already placed in the world, with their mesh giving the materials
+   * and an optional material replacing them
+   */
+  pub fn put_meshes (&mut self, meshes: &[(&[Triangle3D], &Mesh, Option<&Material>)], cam: &Camera, lights: &[LightSource]) {
+    let sort_scope = Profiler::scope(&self.profiler, "sort");
+    // with their distance to the camera, computed once
+    let mut triangles: Vec<(f32, &Triangle3D, &Mesh, Option<&Material>)> = meshes.iter()
+      .flat_map(|&(triangles, mesh, material)| triangles.iter().map(move |triangle| (triangle, mesh, material)))
+      .map(|(triangle, mesh, material)| (self.distance_triangle_camera(*triangle, cam), triangle, mesh, material))
+      .collect();
+    let shading_ramp = self.shading_ramp.clone();
     // sort triangle by distance to draw near traiangle at the end
-    mesh.sort_by(
-      |&a, &b| {
-        let distance_a = self.distance_triangle_camera(a, cam);
-        let distance_b = self.distance_triangle_camera(b, cam);
+    triangles.sort_by(
+      |&(distance_a, _, _, _), &(distance_b, _, _, _)| {
         distance_b.partial_cmp(&distance_a).unwrap_or(std::cmp::Ordering::Equal)
       });
+    self.stats.submitted += triangles.len();
+    drop(sort_scope);
+
+    let clip_scope = Profiler::scope(&self.profiler, "clip");
     let look_at: Vec3 = cam.get_look_at_direction();
-    for triangle in mesh {
+    // clipped triangles facing the camera, with their light intensity and material
+    let mut visible_triangles: Vec<(Triangle3D, f32, Option<&Material>)> = Vec::with_capacity(triangles.len());
+    for (_, &triangle, mesh, material) in triangles {
       // add "Clipping" avoid triangle bug due to the camera
       let clipped_triangle_list = self.clip(triangle, cam, look_at);
       
@@ -176,58 +721,166 @@ impl Engine<'_> {
         let surface_normal: Vec3 = cross_prod(line1, line2);
         
         // add "Face-Culling" to reduce the number of triangle drawn
-        if true || dot(surface_normal, clipped_triangle.v1 - cam.position) < 0.0 {
+        let view_direction = match cam.projection {
+          Projection::Perspective { .. } => clipped_triangle.v1 - cam.position,
+          Projection::Orthographic { .. } => look_at
+        };
+        if !self.face_culling || dot(surface_normal, view_direction) < 0.0 {
           // add light based on the light source and the triangle position
-          let light_char: char = light_source.diffuse_light(surface_normal, clipped_triangle.v1);
-          let transformed_triangle = clipped_triangle.clone()
-            .translate(-1.0 * cam.position)
-            .rotation_y(cam.yaw)
-            .rotation_x(cam.pitch)
-            .projection(cam.focal_length)
-            .to_screen(self);
-          self.put_triangle(&transformed_triangle, light_char);
+          let intensity: f32 = lights.iter()
+            .map(|light_source| light_source.diffuse_intensity(clipped_triangle.normal.unwrap_or(surface_normal), clipped_triangle.v1))
+            .sum::<f32>()
+            .min(1.0);
+          visible_triangles.push((clipped_triangle, intensity, material.or(mesh.material(clipped_triangle.material))));
+        } else {
+          self.stats.culled += 1;
         }
       }
     }
+    drop(clip_scope);
+
+    let project_scope = Profiler::scope(&self.profiler, "project");
+    let screen_triangles: Vec<ScreenTriangle> = visible_triangles.iter()
+      .map(|&(triangle, intensity, material)| {
+        let has_uv = triangle.uv.is_some();
+        let uv = triangle.uv.unwrap_or([Vec2::new(0.0, 0.0); 3]);
+        let view_triangle = triangle
+          .translate(-1.0 * cam.position)
+          .rotation_y(cam.yaw)
+          .rotation_x(cam.pitch);
+        let vertices = [
+          self.to_raster_vertex(view_triangle.v1, cam, uv[0]),
+          self.to_raster_vertex(view_triangle.v2, cam, uv[1]),
+          self.to_raster_vertex(view_triangle.v3, cam, uv[2]),
+        ];
+        ScreenTriangle { vertices, colors: triangle.colors, intensity, material, has_uv }
+      })
+      .collect();
+    self.stats.drawn += screen_triangles.len();
+    drop(project_scope);
+
+    let _raster_scope = Profiler::scope(&self.profiler, "raster");
+
+    let texture_filter = self.texture_filter;
+    match self.render_mode {
+      RenderMode::Filled => {
+        self.put_screen_triangles(&screen_triangles, |triangle, fragment| {
+          let albedo = match triangle.material {
+            Some(material) => material.albedo(triangle.has_uv.then_some(fragment.varying), texture_filter),
+            None => Vec3::new(1.0, 1.0, 1.0)
+          };
+          let albedo = match triangle.colors {
+            Some(colors) => albedo * Vec3::interpolate(colors, fragment.barycentric),
+            None => albedo
+          };
+          let color = triangle.intensity * albedo;
+          Some((shading_char(&shading_ramp, luminance(color)), color))
+        });
+      },
+      RenderMode::Wireframe => self.put_edges(&screen_triangles, false),
+      RenderMode::PointCloud => self.put_points(&screen_triangles),
+      RenderMode::HiddenLine => {
+        // fill the depth buffer only, then draw the visible edges
+        let background = (self.background, self.background_color);
+        self.put_screen_triangles(&screen_triangles, |_, _| Some(background));
+        self.put_edges(&screen_triangles, true);
+      }
+    }
   }
 
-  pub fn play_loop (&mut self, objects: Vec<Triangle3D>) -> io::Result<()> {
-    let mut cam = Camera { position: Vec3 {x: -0.5, y: 0.5, z: -2.0 }, pitch: 0.0, yaw: 0.0, focal_length: 1.0 };
-    let mut last: Instant = Instant::now();
-    let mut light_source: LightSource = LightSource::at(&Vec3::new(5.0, 5.0, 5.0));
-    let mut triangles = objects.clone();
+  pub fn play_loop (&mut self, mut scene: Scene) -> io::Result<()> {
+    let mut terminal = TerminalSession::start(self.controls.mouse.enabled)?;
+    let mut player = Player::new(self.aspect_ratio(), self.controls.clone(), terminal.release_events());
+    // without camera, the scene is seen from slightly above
+    let mut cam = match scene.camera.take() {
+      Some(cam) => cam,
+      None => {
+        let mut cam = Camera { pitch: -0.35, projection: self.projection, ..Camera::default() };
+        player.frame(&mut cam, &scene, None);
+        cam
+      }
+    };
+    // seconds elapsed on the camera path, played from the start when there is one
+    let mut path_time: f32 = 0.0;
+    player.path_playing = scene.camera_path.is_some();
+    if scene.lights.is_empty() {
+      scene.lights.push(LightSource::at(&Vec3::new(5.0, 5.0, 5.0)));
+    }
+    let mut clock = FrameClock::new(self.timing, Instant::now());
+    let mut hud = Hud::new(self.hud.clone());
+    player.hud = self.hud.visible;
     loop {
-      let current_time: Instant = Instant::now();
-      let delta_time: f32 = (current_time - last).as_millis() as f32;
-      last = current_time;
+      let frame_start: Instant = Instant::now();
+      // seconds
+      let delta_time: f32 = clock.tick(frame_start);
+      hud.update(delta_time);
 
-      self.clear(' ');
-      if poll(Duration::from_millis(10))? {
-        _ = player_action(&mut cam, &mut light_source, &mut triangles, delta_time);
+      self.clear(self.background);
+      let input_scope = Profiler::scope(&self.profiler, "input");
+      while poll(Duration::ZERO)? {
+        _ = player_action(&mut cam, &mut player, &mut scene, &mut self.render_mode);
+      }
+      drop(input_scope);
+      if player.quit {
+        return Ok(());
+      }
+      if let Some((width, height)) = player.resize.take() {
+        if self.follow_terminal {
+          // the last line is left to the cursor, the frame would scroll otherwise
+          self.resize(width, height.saturating_sub(1));
+          player.aspect = self.aspect_ratio();
+        }
+        terminal.clear()?;
       }
-      self.put_mesh(triangles.clone(), &cam, &light_source);
+      if !player.paused {
+        let _update_scope = Profiler::scope(&self.profiler, "update");
+        let (steps, step) = clock.steps(delta_time);
+        for _ in 0..steps {
+          player.update(&mut cam, &mut scene.lights, step);
+          if let Some(camera_path) = scene.camera_path.as_ref().filter(|_| player.path_playing) {
+            if camera_path.is_finished(path_time) {
+              // played again from the start
+              path_time = 0.0;
+            }
+            camera_path.apply(&mut cam, path_time);
+            path_time += step;
+            player.path_playing = !camera_path.is_finished(path_time);
+          }
+        }
+      }
+      self.put_scene(&scene, &cam);
+      if player.hud {
+        hud.put(self, &cam);
+      }
+      let present_scope = Profiler::scope(&self.profiler, "present");
       self.draw();
-      self.logger.log(format!("yaw: {:?}, pitch: {:?}, position: {:?}, delta_time= {:?}, current_time={:?}", cam.yaw, cam.pitch, cam.position, delta_time, (current_time - last).as_millis() as f32));
+      drop(present_scope);
+      self.profiler.end_frame();
+      log_trace!(self.logger, "yaw: {:?}, pitch: {:?}, position: {:?}, delta_time= {:?}", cam.yaw, cam.pitch, cam.position, delta_time);
+      clock.wait(frame_start);
     }
   }
 
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Camera {
   pub position: Vec3,
   pub pitch: f32,
   pub yaw: f32,
-  pub focal_length: f32
+  pub projection: Projection
+}
+
+impl Default for Camera {
+  fn default () -> Self {
+    Camera::new(Vec3::new(-0.5, 0.5, -2.0), 0.0, 0.0, Projection::default())
+  }
 }
 
 impl Camera {
-  pub fn new (position: Vec3, pitch: f32, yaw: f32, focal_length: f32) -> Camera {
-    if focal_length < 1.0 {
-      panic!("The focal_length must be upper than 1.0");
-    }
-    Camera {position, pitch, yaw, focal_length}
+  pub fn new (position: Vec3, pitch: f32, yaw: f32, projection: Projection) -> Camera {
+    Camera {position, pitch, yaw, projection}
   }
   pub fn get_look_at_direction (&self) -> Vec3 {
     Vec3 {
@@ -249,105 +902,206 @@ impl Camera {
       z: f32::sin(self.yaw)
     }
   }
-  pub fn move_from_inputs (&mut self, delta_time: f32) -> io::Result<()> {
-    let forward_direction = self.get_forward_direction();
-    let right_direction = self.get_right_direction();
-    match read()? {
-      Event::Key(event) => {
-        match event.code {
-          KeyCode::Down => {
-            if self.pitch > -1.57 {
-              self.pitch -= 0.01*delta_time;
-            }
-          },
-          KeyCode::Up => {
-            if self.pitch < 1.57 {
-              self.pitch += 0.01*delta_time;
-            }
-          },
-          KeyCode::Left => {
-            self.yaw += 0.01*delta_time;
-          },
-          KeyCode::Right => {
-            self.yaw -= 0.01*delta_time;
-          },
-          KeyCode::Char('z') => {
-            self.position += forward_direction*0.01*delta_time;
-          },
-          KeyCode::Char('s') => {
-            self.position -= forward_direction*0.01*delta_time;
-          },
-          KeyCode::Char('q') => {
-            self.position -= right_direction*0.01*delta_time;
-          },
-          KeyCode::Char('d') => {
-            self.position += right_direction*0.01*delta_time;
-          },
-          KeyCode::Char(' ') => {
-            if event.modifiers == KeyModifiers::CONTROL {
-              self.position.y -= 0.01*delta_time;
-            } else {
-              self.position.y += 0.01*delta_time;
-            }
-          },
-          _ => {}
-        }
+  pub fn get_up_direction (&self) -> Vec3 {
+    Vec3 {
+      x: f32::sin(self.yaw)*f32::sin(self.pitch),
+      y: f32::cos(self.pitch),
+      z: -f32::cos(self.yaw)*f32::sin(self.pitch)
+    }
+  }
+  /*
+   * Move the camera back along its direction until the box (min, max) fills the view,
+   * `aspect` is the width divided by the height of the view
+   */
+  pub fn frame (&mut self, min: Vec3, max: Vec3, aspect: f32) {
+    let center = 0.5 * (min + max);
+    let radius = (0.5 * (max - min)).length().max(0.01);
+    let distance = match self.projection {
+      Projection::Perspective { .. } => {
+        let (tan_x, tan_y) = self.projection.half_extent(1.0, aspect);
+        radius / tan_x.min(tan_y).atan().sin()
       },
-      // Event::Mouse(event) => {},
-      // Event::Paste(data) => {},
-      // Event::Resize(width, height) => {},
-      // Event::FocusGained => {},
-      // Event::FocusLost => {},
-      _ => {},
+      Projection::Orthographic { .. } => {
+        self.projection = Projection::Orthographic { height: 2.0 * radius * (1.0 / aspect).max(1.0) };
+        radius + 1.0
+      }
+    };
+    self.position = center - self.get_look_at_direction() * distance;
+  }
+  /*
+   * Turn the camera toward `target`, kept as is when it is on the target
+   */
+  pub fn look_at (&mut self, target: Vec3) {
+    let direction = target - self.position;
+    if direction.length2() <= f32::EPSILON {
+      return;
     }
-    Ok(())
+    let direction = direction.normalize();
+    self.pitch = direction.y.clamp(-1.0, 1.0).asin();
+    self.yaw = f32::atan2(-direction.x, direction.z);
   }
 }
 
+#[derive(Debug, Clone)]
 pub struct LightSource {
   light_gradient: Vec<char>,
   pub position: Vec3,
 }
 
+impl Default for LightSource {
+  fn default () -> Self {
+    Self::new()
+  }
+}
+
 impl LightSource {
   pub fn new () -> LightSource {
     LightSource {
-      light_gradient: vec!['.', ',', ';', 'l', 'a', '#', '@'],
+      light_gradient: DEFAULT_SHADING_RAMP.chars().collect(),
       position: Vec3 { x: 0.0, y: 0.0, z: 0.0 }
     }
   }
   pub fn at (position: &Vec3) -> LightSource {
     LightSource {
-      light_gradient: vec!['.', ',', ';', 'l', 'a', '#', '@'],
+      light_gradient: DEFAULT_SHADING_RAMP.chars().collect(),
       position: *position
     }
   }
   pub fn diffuse_light (&self, normal_surface: Vec3, vertex: Vec3) -> char {
+    self.light_char(self.diffuse_intensity(normal_surface, vertex))
+  }
+  /*
+   * Lambert term between 0.0 (back to the light) and 1.0
+   */
+  pub fn diffuse_intensity (&self, normal_surface: Vec3, vertex: Vec3) -> f32 {
     let light_direction: Vec3 = self.position - vertex;
-    let intensity: f32 = dot(light_direction.normalize(), normal_surface.normalize());
-    if intensity >= 0.0 {
-      let char_number: f32 = self.light_gradient.len() as f32 - 1.0;
-      let symbol_idx_real: f32 = intensity * (char_number);
-      let symbol_idx: usize = symbol_idx_real.round() as usize;
-      return self.light_gradient[symbol_idx]
-    }
-    return '.'
+    dot(light_direction.normalize(), normal_surface.normalize()).max(0.0)
+  }
+  pub fn light_char (&self, intensity: f32) -> char {
+    shading_char(&self.light_gradient, intensity)
   }
   pub fn move_position (&mut self, position: Vec3) {
     self.position = position;
   }
-  pub fn move_in_circle(&mut self, delta_time: f32) {
-    let center: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
-    let radius: f32 = 2.5;
-    let speed: f32 = 10.5; // radius per second
-    let height: f32 = 5.0;
+  /*
+   * Turn the light around the vertical axis through the origin for `delta_time` seconds
+   */
+  pub fn move_in_circle (&mut self, delta_time: f32) {
+    // radians per second
+    let speed: f32 = 1.5;
+    let (sin, cos) = (speed * delta_time).sin_cos();
+    let Vec3 { x, y, z } = self.position;
+    self.move_position(Vec3::new(x * cos - z * sin, y, x * sin + z * cos));
+  }
+}
+
+/*
+ * Char of the ramp for an intensity between 0.0 and 1.0
+ */
+pub fn shading_char (ramp: &[char], intensity: f32) -> char {
+  match ramp.last() {
+    Some(_) if intensity >= 0.0 => {
+      let index = (intensity.min(1.0) * (ramp.len() - 1) as f32).round() as usize;
+      ramp[index]
+    },
+    _ => '.'
+  }
+}
 
-    let angle = (speed * delta_time) % (2.0 * std::f32::consts::PI);
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  type Frame = (Vec<char>, Vec<f32>, Vec<(f32, f32, f32)>);
+
+  fn frame (engine: &Engine) -> Frame {
+    let colors = engine.color_buffer.iter().map(|color| (color.x, color.y, color.z)).collect();
+    (engine.pixel_buffer.clone(), engine.depth_buffer.clone(), colors)
+  }
+
+  /*
+   * Frame drawn with `threads`, 100x30 cells being 4 columns and 4 bands of tiles
+   */
+  fn render (threads: usize, draw: impl Fn(&mut Engine)) -> Frame {
+    let mut logger = Logger::new();
+    logger.disable_log();
+    let mut engine = Engine::new(100, 30, &logger);
+    engine.threads = threads;
+    engine.clear(' ');
+    draw(&mut engine);
+    frame(&engine)
+  }
 
-    let new_position = Vec3::new(
-        center.x + radius * angle.cos(),
-        center.y + height,
-        center.z + radius * angle.sin());
-    self.move_position(self.position + new_position);
+  #[test]
+  fn tiles_draw_the_single_threaded_frame () {
+    // corners in cells and distance, across the borders of the tiles (x = 32, 64, 96) and bands (y = 8, 16, 24)
+    let corners = [
+      [(0.0, 0.0, 2.0), (100.0, 30.0, 2.0), (0.0, 30.0, 2.0)],
+      // same depth: the first one drawn keeps the cells
+      [(0.0, 0.0, 2.0), (100.0, 0.0, 2.0), (100.0, 30.0, 2.0)],
+      [(0.0, 0.0, 2.0), (100.0, 0.0, 2.0), (100.0, 30.0, 2.0)],
+      [(30.0, 6.0, 1.0), (70.0, 7.0, 4.0), (34.0, 20.0, 1.5)],
+      [(32.0, 8.0, 1.0), (64.0, 8.0, 1.0), (32.0, 16.0, 1.0)],
+      // thin along a band border
+      [(10.0, 7.5, 1.2), (90.0, 8.5, 1.8), (10.0, 8.6, 1.2)],
+      [(95.0, 2.0, 1.1), (99.9, 29.0, 3.0), (60.0, 25.0, 1.1)],
+    ];
+    let triangles: Vec<ScreenTriangle> = corners.iter().enumerate().map(|(index, corners)| ScreenTriangle {
+      vertices: corners.map(|(x, y, z)| RasterVertex::new(Vec2::new(x, y), z, Vec2::new(x / 100.0, y / 30.0))),
+      colors: None,
+      intensity: index as f32,
+      material: None,
+      has_uv: false
+    }).collect();
+    let draw = |engine: &mut Engine| engine.put_screen_triangles(&triangles, |triangle, fragment| {
+      let char = (b'a' + triangle.intensity as u8) as char;
+      Some((char, Vec3::new(fragment.barycentric[0], fragment.varying.x, fragment.varying.y)))
+    });
+    let single = render(1, draw);
+    assert!(single.0.iter().all(|&char| char != ' '));
+    assert!(single.0.contains(&'b') && !single.0.contains(&'c'));
+    assert_eq!(render(4, draw), single);
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn tiles_draw_the_single_threaded_mesh () {
+    let mut triangles = vec![];
+    for i in 0..8 {
+      for j in 0..8 {
+        let (x, y, z) = (i as f32 * 0.4 - 2.0, j as f32 * 0.3 - 1.0, ((i * 3 + j * 5) % 7) as f32 * 0.1);
+        triangles.push(Triangle3D::new(Vec3::new(x, y, z), Vec3::new(x + 0.9, y + 0.1, z + 0.2), Vec3::new(x + 0.2, y + 0.7, z)));
+      }
+    }
+    let mesh = Mesh::new(triangles);
+    let draw = |engine: &mut Engine| engine.put_mesh(&mesh, &Camera::default(), &LightSource::new());
+    let single = render(1, draw);
+    assert!(single.0.iter().filter(|&&char| char != ' ').count() > 500);
+    assert_eq!(render(4, draw), single);
+  }
+
+  #[test]
+  fn scene_caches_follow_the_meshes () {
+    let mut logger = Logger::new();
+    logger.disable_log();
+    let triangle = |x: f32| Triangle3D::new(Vec3::new(x, 0.0, 0.0), Vec3::new(x + 1.0, 0.0, 0.0), Vec3::new(x, 1.0, 0.0));
+    let mut scene = Scene::from_mesh("triangle", Mesh::new(vec![triangle(-1.0)]));
+    scene.lights.push(LightSource::new());
+    let mut engine = Engine::new(100, 30, &logger);
+    let draw = |engine: &mut Engine, scene: &Scene| {
+      engine.clear(' ');
+      engine.put_scene(scene, &Camera::default());
+      frame(engine)
+    };
+    let first = draw(&mut engine, &scene);
+
+    // same number of triangles, moved out of the former bounding sphere
+    scene.mesh_mut(0).triangles[0] = triangle(0.5);
+    let edited = draw(&mut engine, &scene);
+    assert_ne!(edited, first);
+    assert_eq!(edited, draw(&mut Engine::new(100, 30, &logger), &scene));
+
+    let other = Scene::from_mesh("other", Mesh::new(vec![triangle(-1.5)]));
+    assert_eq!(draw(&mut engine, &other), draw(&mut Engine::new(100, 30, &logger), &other));
+  }
+
+}
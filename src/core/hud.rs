@@ -0,0 +1,172 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use super::engine::{Camera, Engine};
+
+/*
+ * Work of the last frame: the times of its stages and what became of the submitted triangles
+ */
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+  // stage times of the previous frame from the profiler, the frame is presented after its rendering
+  pub times: Vec<(&'static str, Duration)>,
+  pub submitted: usize,
+  // out of the view or back faces
+  pub culled: usize,
+  // cut or removed by the near or far plane
+  pub clipped: usize,
+  pub drawn: usize
+}
+
+/*
+ * Lines the overlay can show
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HudItem {
+  Fps,
+  Times,
+  Triangles,
+  Camera
+}
+
+impl FromStr for HudItem {
+  type Err = String;
+
+  fn from_str (value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "fps" => Ok(HudItem::Fps),
+      "times" => Ok(HudItem::Times),
+      "triangles" => Ok(HudItem::Triangles),
+      "camera" => Ok(HudItem::Camera),
+      _ => Err(format!("Unknown HUD item '{}' (fps, times, triangles, camera)", value))
+    }
+  }
+}
+
+impl fmt::Display for HudItem {
+  fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      HudItem::Fps => write!(f, "fps"),
+      HudItem::Times => write!(f, "times"),
+      HudItem::Triangles => write!(f, "triangles"),
+      HudItem::Camera => write!(f, "camera")
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum HudCorner {
+  #[default]
+  TopLeft,
+  TopRight,
+  BottomLeft,
+  BottomRight
+}
+
+impl FromStr for HudCorner {
+  type Err = String;
+
+  fn from_str (value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "top_left" => Ok(HudCorner::TopLeft),
+      "top_right" => Ok(HudCorner::TopRight),
+      "bottom_left" => Ok(HudCorner::BottomLeft),
+      "bottom_right" => Ok(HudCorner::BottomRight),
+      _ => Err(format!("Unknown HUD corner '{}' (top_left, top_right, bottom_left, bottom_right)", value))
+    }
+  }
+}
+
+impl fmt::Display for HudCorner {
+  fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      HudCorner::TopLeft => write!(f, "top_left"),
+      HudCorner::TopRight => write!(f, "top_right"),
+      HudCorner::BottomLeft => write!(f, "bottom_left"),
+      HudCorner::BottomRight => write!(f, "bottom_right")
+    }
+  }
+}
+
+/*
+ * Which lines are shown, in which order and in which corner of the frame
+ */
+#[derive(Debug, Clone)]
+pub struct HudSettings {
+  pub visible: bool,
+  pub items: Vec<HudItem>,
+  pub corner: HudCorner
+}
+
+impl Default for HudSettings {
+  fn default () -> Self {
+    HudSettings {
+      visible: false,
+      items: vec![HudItem::Fps, HudItem::Times, HudItem::Triangles, HudItem::Camera],
+      corner: HudCorner::default()
+    }
+  }
+}
+
+/*
+ * Text overlay drawn on top of the frame
+ */
+#[derive(Debug)]
+pub struct Hud {
+  pub settings: HudSettings,
+  // frames per second, smoothed over the last frames
+  fps: f32
+}
+
+impl Hud {
+  pub fn new (settings: HudSettings) -> Hud {
+    Hud { settings, fps: 0.0 }
+  }
+
+  /*
+   * Account for a frame of `delta_time` seconds
+   */
+  pub fn update (&mut self, delta_time: f32) {
+    if delta_time > 0.0 {
+      let fps = 1.0 / delta_time;
+      self.fps = if self.fps == 0.0 { fps } else { 0.9 * self.fps + 0.1 * fps };
+    }
+  }
+
+  pub fn lines (&self, stats: &FrameStats, cam: &Camera) -> Vec<String> {
+    let ms = |duration: Duration| duration.as_secs_f32() * 1000.0;
+    self.settings.items.iter()
+      .map(|item| match item {
+        HudItem::Fps => format!("FPS {:.1}", self.fps),
+        HudItem::Times => stats.times.iter()
+          .map(|(stage, time)| format!("{} {:.1}ms", stage, ms(*time)))
+          .collect::<Vec<_>>()
+          .join(" "),
+        HudItem::Triangles => format!(
+          "triangles {} culled {} clipped {} drawn {}",
+          stats.submitted, stats.culled, stats.clipped, stats.drawn),
+        HudItem::Camera => format!(
+          "position ({:.2}, {:.2}, {:.2}) yaw {:.2} pitch {:.2}",
+          cam.position.x, cam.position.y, cam.position.z, cam.yaw, cam.pitch)
+      })
+      .collect()
+  }
+
+  /*
+   * Write the lines in the frame buffer, over the rendered scene
+   */
+  pub fn put (&self, engine: &mut Engine, cam: &Camera) {
+    let lines = self.lines(&engine.stats, cam);
+    let top = match self.settings.corner {
+      HudCorner::TopLeft | HudCorner::TopRight => 0,
+      HudCorner::BottomLeft | HudCorner::BottomRight => engine.height.saturating_sub(lines.len())
+    };
+    for (row, line) in lines.iter().enumerate() {
+      let length = line.chars().count();
+      let left = match self.settings.corner {
+        HudCorner::TopLeft | HudCorner::BottomLeft => 0,
+        HudCorner::TopRight | HudCorner::BottomRight => engine.width.saturating_sub(length)
+      };
+      engine.put_text(left, top + row, line);
+    }
+  }
+}
@@ -0,0 +1,58 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use crossterm::event::{KeyEvent, KeyEventKind};
+
+use super::bindings::Key;
+
+/*
+ * Keys currently held down. When the terminal reports the releases (kitty keyboard protocol, Windows)
+ * a key is held until its release, elsewhere until no press nor repeat came for `timeout`.
+ */
+#[derive(Debug, Clone)]
+pub struct InputState {
+  held: HashMap<Key, Instant>,
+  pub release_events: bool,
+  pub timeout: Duration
+}
+
+impl InputState {
+  pub fn new (release_events: bool, timeout: Duration) -> InputState {
+    InputState { held: HashMap::new(), release_events, timeout }
+  }
+
+  /*
+   * Record the event, true when it is a new press (not a repeat nor a release)
+   */
+  pub fn handle (&mut self, event: &KeyEvent, now: Instant) -> bool {
+    let key = Key::from_event(event);
+    match event.kind {
+      KeyEventKind::Release => {
+        self.held.remove(&key);
+        false
+      },
+      KeyEventKind::Repeat => {
+        self.held.insert(key, now);
+        false
+      },
+      KeyEventKind::Press => self.held.insert(key, now).is_none()
+    }
+  }
+
+  /*
+   * Forget the keys whose repeats stopped, without release events
+   */
+  pub fn update (&mut self, now: Instant) {
+    if !self.release_events {
+      let timeout = self.timeout;
+      self.held.retain(|_, last| now.duration_since(*last) <= timeout);
+    }
+  }
+
+  pub fn held (&self) -> impl Iterator<Item = &Key> {
+    self.held.keys()
+  }
+
+  pub fn clear (&mut self) {
+    self.held.clear();
+  }
+}
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use super::{math::vector::{Vec2, Vec3}, texture::{Texture, TextureFilter}};
+
+#[derive(Debug, Clone)]
+pub struct Material {
+  pub name: String,
+  pub diffuse: Vec3,
+  pub texture: Option<Arc<Texture>>
+}
+
+impl Material {
+  pub fn new (name: &str) -> Material {
+    Material {
+      name: String::from(name),
+      diffuse: Vec3::new(1.0, 1.0, 1.0),
+      texture: None
+    }
+  }
+
+  /*
+   * Base colour of the surface before lighting
+   */
+  pub fn albedo (&self, uv: Option<Vec2>, filter: TextureFilter) -> Vec3 {
+    match (&self.texture, uv) {
+      (Some(texture), Some(uv)) => self.diffuse * texture.sample(uv, filter),
+      _ => self.diffuse
+    }
+  }
+}
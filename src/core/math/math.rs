@@ -18,14 +18,21 @@ pub fn cross_prod (v1: Vec3, v2: Vec3) -> Vec3 {
   }
 }
 
-pub fn line_plane_intersection (normal_plane: Vec3, normal_point: Vec3, v1: Vec3, v2: Vec3) -> Vec3 {
-  let mut u: Vec3 = v2 - v1;
-  let dot_p: f32 = dot (normal_plane, u);
+/*
+ * Position of the intersection on the segment [v1, v2] (0.0 is v1, 1.0 is v2)
+ */
+pub fn line_plane_parameter (normal_plane: Vec3, normal_point: Vec3, v1: Vec3, v2: Vec3) -> Option<f32> {
+  let dot_p: f32 = dot (normal_plane, v2 - v1);
   if dot_p.abs() < 0.00001 {
-    return Vec3::new(0.0, 0.0, 0.0);
+    return None;
   }
   let w: Vec3 = v1 - normal_point;
-  let si: f32 = - dot(normal_plane, w) / dot_p;
-  u = si*u;
-  v1 + u
+  Some(- dot(normal_plane, w) / dot_p)
+}
+
+pub fn line_plane_intersection (normal_plane: Vec3, normal_point: Vec3, v1: Vec3, v2: Vec3) -> Vec3 {
+  match line_plane_parameter(normal_plane, normal_point, v1, v2) {
+    Some(si) => v1 + si*(v2 - v1),
+    None => Vec3::new(0.0, 0.0, 0.0)
+  }
 }
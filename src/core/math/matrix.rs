@@ -0,0 +1,142 @@
+use std::ops;
+
+use super::{quaternion::Quat, vector::Vec3};
+
+/*
+ * 4x4 matrix (row-major) applied to column vectors: p' = M * p
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat4 {
+  pub m: [[f32; 4]; 4]
+}
+
+impl ops::Mul<Mat4> for Mat4 {
+  type Output = Mat4;
+  fn mul(self, rhs: Mat4) -> Self::Output {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+      for (j, value) in row.iter_mut().enumerate() {
+        *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+      }
+    }
+    Mat4 { m }
+  }
+}
+
+impl Default for Mat4 {
+  fn default () -> Self {
+    Mat4::identity()
+  }
+}
+
+impl Mat4 {
+  pub fn identity () -> Mat4 {
+    Mat4 { m: [
+      [1.0, 0.0, 0.0, 0.0],
+      [0.0, 1.0, 0.0, 0.0],
+      [0.0, 0.0, 1.0, 0.0],
+      [0.0, 0.0, 0.0, 1.0]
+    ] }
+  }
+
+  pub fn translation (v: Vec3) -> Mat4 {
+    Mat4 { m: [
+      [1.0, 0.0, 0.0, v.x],
+      [0.0, 1.0, 0.0, v.y],
+      [0.0, 0.0, 1.0, v.z],
+      [0.0, 0.0, 0.0, 1.0]
+    ] }
+  }
+
+  pub fn scaling (v: Vec3) -> Mat4 {
+    Mat4 { m: [
+      [v.x, 0.0, 0.0, 0.0],
+      [0.0, v.y, 0.0, 0.0],
+      [0.0, 0.0, v.z, 0.0],
+      [0.0, 0.0, 0.0, 1.0]
+    ] }
+  }
+
+  /*
+   * Rotation of the unit quaternion, as Quat::rotate
+   */
+  pub fn rotation (q: Quat) -> Mat4 {
+    let Quat { w, x, y, z } = q;
+    Mat4 { m: [
+      [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+      [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+      [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+      [0.0, 0.0, 0.0, 1.0]
+    ] }
+  }
+
+  /*
+   * Same convention as Vec3::rotation_x
+   */
+  pub fn rotation_x (pitch: f32) -> Mat4 {
+    let (sin, cos) = pitch.sin_cos();
+    Mat4 { m: [
+      [1.0, 0.0, 0.0, 0.0],
+      [0.0, cos, -sin, 0.0],
+      [0.0, sin, cos, 0.0],
+      [0.0, 0.0, 0.0, 1.0]
+    ] }
+  }
+
+  /*
+   * Same convention as Vec3::rotation_y
+   */
+  pub fn rotation_y (yaw: f32) -> Mat4 {
+    let (sin, cos) = yaw.sin_cos();
+    Mat4 { m: [
+      [cos, 0.0, sin, 0.0],
+      [0.0, 1.0, 0.0, 0.0],
+      [-sin, 0.0, cos, 0.0],
+      [0.0, 0.0, 0.0, 1.0]
+    ] }
+  }
+
+  pub fn rotation_z (roll: f32) -> Mat4 {
+    let (sin, cos) = roll.sin_cos();
+    Mat4 { m: [
+      [cos, -sin, 0.0, 0.0],
+      [sin, cos, 0.0, 0.0],
+      [0.0, 0.0, 1.0, 0.0],
+      [0.0, 0.0, 0.0, 1.0]
+    ] }
+  }
+
+  pub fn transform_point (&self, p: Vec3) -> Vec3 {
+    let m = &self.m;
+    Vec3 {
+      x: m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+      y: m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+      z: m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3]
+    }
+  }
+
+  /*
+   * Greatest stretching applied by the matrix (length of the longest axis)
+   */
+  pub fn max_scale (&self) -> f32 {
+    (0..3)
+      .map(|column| self.transform_vector(Vec3::new(
+        if column == 0 { 1.0 } else { 0.0 },
+        if column == 1 { 1.0 } else { 0.0 },
+        if column == 2 { 1.0 } else { 0.0 }
+      )).length())
+      .fold(0.0, f32::max)
+  }
+
+  /*
+   * Direction, the translation is ignored
+   */
+  pub fn transform_vector (&self, v: Vec3) -> Vec3 {
+    let m = &self.m;
+    Vec3 {
+      x: m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+      y: m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+      z: m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z
+    }
+  }
+}
@@ -1,3 +1,6 @@
+#[allow(clippy::module_inception)]
 pub mod math;
 pub mod vector;
-pub mod triangle;
\ No newline at end of file
+pub mod triangle;
+pub mod matrix;
+pub mod quaternion;
\ No newline at end of file
@@ -0,0 +1,98 @@
+use std::ops;
+
+use super::vector::Vec3;
+
+/*
+ * Unit quaternion representing a rotation
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quat {
+  pub w: f32,
+  pub x: f32,
+  pub y: f32,
+  pub z: f32
+}
+
+impl ops::Mul<Quat> for Quat {
+  type Output = Quat;
+  fn mul(self, rhs: Quat) -> Self::Output {
+    Quat {
+      w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+      x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+      y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+      z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w
+    }
+  }
+}
+
+impl Quat {
+  pub fn identity () -> Quat {
+    Quat { w: 1.0, x: 0.0, y: 0.0, z: 0.0 }
+  }
+
+  pub fn from_axis_angle (axis: Vec3, angle: f32) -> Quat {
+    let axis = axis.normalize();
+    let (sin, cos) = (angle / 2.0).sin_cos();
+    Quat { w: cos, x: axis.x * sin, y: axis.y * sin, z: axis.z * sin }
+  }
+
+  /*
+   * Orientation of a camera with the angles of `Camera` (looking along +z without rotation)
+   */
+  pub fn from_yaw_pitch (yaw: f32, pitch: f32) -> Quat {
+    Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), -yaw) * Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), -pitch)
+  }
+
+  /*
+   * (yaw, pitch) of the direction given by the rotation of +z, the roll is lost
+   */
+  pub fn to_yaw_pitch (self) -> (f32, f32) {
+    let direction = self.rotate(Vec3::new(0.0, 0.0, 1.0));
+    (f32::atan2(-direction.x, direction.z), direction.y.clamp(-1.0, 1.0).asin())
+  }
+
+  pub fn dot (self, q: Quat) -> f32 {
+    self.w * q.w + self.x * q.x + self.y * q.y + self.z * q.z
+  }
+
+  pub fn normalize (self) -> Quat {
+    let norm = self.dot(self).sqrt();
+    Quat { w: self.w / norm, x: self.x / norm, y: self.y / norm, z: self.z / norm }
+  }
+
+  pub fn conjugate (self) -> Quat {
+    Quat { w: self.w, x: -self.x, y: -self.y, z: -self.z }
+  }
+
+  pub fn rotate (self, v: Vec3) -> Vec3 {
+    let p = self * Quat { w: 0.0, x: v.x, y: v.y, z: v.z } * self.conjugate();
+    Vec3::new(p.x, p.y, p.z)
+  }
+
+  /*
+   * Spherical interpolation from `self` (t = 0) to `q` (t = 1) along the shortest arc
+   */
+  pub fn slerp (self, q: Quat, t: f32) -> Quat {
+    let mut cos = self.dot(q);
+    let q = if cos < 0.0 {
+      cos = -cos;
+      Quat { w: -q.w, x: -q.x, y: -q.y, z: -q.z }
+    } else {
+      q
+    };
+    // nearly the same rotation: a linear interpolation avoids the division by sin(0)
+    let (a, b) = if cos > 0.9995 {
+      (1.0 - t, t)
+    } else {
+      let angle = cos.acos();
+      let sin = angle.sin();
+      (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+    };
+    Quat {
+      w: a * self.w + b * q.w,
+      x: a * self.x + b * q.x,
+      y: a * self.y + b * q.y,
+      z: a * self.z + b * q.z
+    }.normalize()
+  }
+}
@@ -1,6 +1,6 @@
 use crate::core::engine::Engine;
 
-use super::vector::{Vec2, Vec3};
+use super::{matrix::Mat4, vector::{Vec2, Vec3}};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Triangle2D {
@@ -25,7 +25,7 @@ impl Triangle2D {
     self.v3.x = x;
     self.v3.y = y;
   }
-  pub fn to_screen (&mut self, engine: &Engine) -> Triangle2D {
+  pub fn to_screen (self, engine: &Engine) -> Triangle2D {
     Triangle2D {
       v1: self.v1.to_screen(engine),
       v2: self.v2.to_screen(engine),
@@ -38,38 +38,63 @@ impl Triangle2D {
 pub struct Triangle3D {
   pub v1: Vec3,
   pub v2: Vec3,
-  pub v3: Vec3
+  pub v3: Vec3,
+  pub uv: Option<[Vec2; 3]>,
+  // colour of each vertex, multiplied with the material one
+  pub colors: Option<[Vec3; 3]>,
+  // normal of the face given by the file (STL facets), lit instead of the winding one
+  pub normal: Option<Vec3>,
+  pub material: Option<usize>
 }
 impl Triangle3D {
   pub fn new (v1: Vec3, v2: Vec3, v3: Vec3) -> Triangle3D {
-    Triangle3D { v1, v2, v3 }
+    Triangle3D { v1, v2, v3, uv: None, colors: None, normal: None, material: None }
   }
-  pub fn projection (&mut self, focal_length: f32) -> Triangle2D {
-    Triangle2D {
-      v1: self.v1.projection(focal_length),
-      v2: self.v2.projection(focal_length),
-      v3: self.v3.projection(focal_length)
-    }
+  pub fn with_uv (self, uv: [Vec2; 3]) -> Triangle3D {
+    Triangle3D { uv: Some(uv), ..self }
+  }
+  pub fn with_colors (self, colors: [Vec3; 3]) -> Triangle3D {
+    Triangle3D { colors: Some(colors), ..self }
+  }
+  pub fn with_normal (self, normal: Option<Vec3>) -> Triangle3D {
+    Triangle3D { normal, ..self }
   }
-  pub fn translate (&mut self, v: Vec3) -> Triangle3D {
+  pub fn with_material (self, material: Option<usize>) -> Triangle3D {
+    Triangle3D { material, ..self }
+  }
+  pub fn translate (self, v: Vec3) -> Triangle3D {
     Triangle3D {
       v1: self.v1 + v,
       v2: self.v2 + v,
-      v3: self.v3 + v
+      v3: self.v3 + v,
+      ..self
+    }
+  }
+  pub fn transform (self, matrix: &Mat4) -> Triangle3D {
+    Triangle3D {
+      v1: matrix.transform_point(self.v1),
+      v2: matrix.transform_point(self.v2),
+      v3: matrix.transform_point(self.v3),
+      normal: self.normal.map(|normal| matrix.transform_vector(normal)),
+      ..self
     }
   }
-  pub fn rotation_x (&mut self, pitch:f32) -> Triangle3D {
+  pub fn rotation_x (self, pitch:f32) -> Triangle3D {
     Triangle3D {
       v1: self.v1.rotation_x(pitch),
       v2: self.v2.rotation_x(pitch),
-      v3: self.v3.rotation_x(pitch)
+      v3: self.v3.rotation_x(pitch),
+      normal: self.normal.map(|normal| normal.rotation_x(pitch)),
+      ..self
     }
   }
-  pub fn rotation_y (&mut self, yaw:f32) -> Triangle3D {
+  pub fn rotation_y (self, yaw:f32) -> Triangle3D {
     Triangle3D {
       v1: self.v1.rotation_y(yaw),
       v2: self.v2.rotation_y(yaw),
-      v3: self.v3.rotation_y(yaw)
+      v3: self.v3.rotation_y(yaw),
+      normal: self.normal.map(|normal| normal.rotation_y(yaw)),
+      ..self
     }
   }
 }
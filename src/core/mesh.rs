@@ -0,0 +1,80 @@
+use super::{material::Material, math::{matrix::Mat4, triangle::Triangle3D, vector::Vec3}};
+
+/*
+ * Vertex drawn on its own, lit through its normal when it has one
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct Point {
+  pub position: Vec3,
+  pub normal: Option<Vec3>,
+  pub color: Option<Vec3>
+}
+
+impl Point {
+  pub fn new (position: Vec3) -> Point {
+    Point { position, normal: None, color: None }
+  }
+
+  pub fn transform (self, matrix: &Mat4) -> Point {
+    Point {
+      position: matrix.transform_point(self.position),
+      // exact for rotations and uniform scales
+      normal: self.normal.map(|normal| matrix.transform_vector(normal)),
+      ..self
+    }
+  }
+}
+
+/*
+ * Triangles, and points for a point cloud
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+  pub triangles: Vec<Triangle3D>,
+  pub points: Vec<Point>,
+  pub materials: Vec<Material>
+}
+
+impl Mesh {
+  pub fn new (triangles: Vec<Triangle3D>) -> Mesh {
+    Mesh { triangles, points: vec![], materials: vec![] }
+  }
+
+  pub fn from_points (points: Vec<Point>) -> Mesh {
+    Mesh { points, ..Mesh::default() }
+  }
+
+  /*
+   * Add the triangles, points and materials of `other`, its material indices shifted after ours
+   */
+  pub fn append (&mut self, other: Mesh) {
+    let offset = self.materials.len();
+    self.triangles.extend(other.triangles.into_iter()
+      .map(|triangle| triangle.with_material(triangle.material.map(|index| offset + index))));
+    self.points.extend(other.points);
+    self.materials.extend(other.materials);
+  }
+
+  pub fn material (&self, index: Option<usize>) -> Option<&Material> {
+    index.and_then(|index| self.materials.get(index))
+  }
+
+  /*
+   * Sphere (centre, radius) containing every vertex and point, centred on the bounding box
+   */
+  pub fn bounding_sphere (&self) -> (Vec3, f32) {
+    let vertices = || self.triangles.iter()
+      .flat_map(|triangle| [triangle.v1, triangle.v2, triangle.v3])
+      .chain(self.points.iter().map(|point| point.position));
+    let Some(first) = vertices().next() else {
+      return (Vec3::new(0.0, 0.0, 0.0), 0.0);
+    };
+    let (min, max) = vertices().fold((first, first), |(min, max), v| (
+      Vec3::new(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z)),
+      Vec3::new(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z))
+    ));
+    let center = 0.5 * (min + max);
+    let radius = vertices().map(|v| (v - center).length2()).fold(0.0, f32::max).sqrt();
+    (center, radius)
+  }
+}
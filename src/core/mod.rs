@@ -1,3 +1,18 @@
 pub mod engine;
 pub mod player;
-pub mod math;
\ No newline at end of file
+pub mod math;
+pub mod raster;
+pub mod texture;
+pub mod material;
+pub mod mesh;
+pub mod tiles;
+pub mod scene;
+pub mod projection;
+pub mod movements;
+pub mod camera_path;
+pub mod bindings;
+pub mod input;
+pub mod terminal;
+pub mod hud;
+pub mod profiler;
+pub mod timing;
\ No newline at end of file
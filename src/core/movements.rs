@@ -1,23 +1,120 @@
-use core::f32;
+use super::{engine::Camera, math::vector::Vec3, projection::Projection};
 
-use super::{engine::{Camera}, math::vector::Vec3};
+// pitch limit keeping the camera away from the vertical
+const MAX_PITCH: f32 = 1.57;
 
+/*
+ * Way the camera follows the inputs, the amounts are given by the caller
+ * (angles in radians, distances in world units, zoom factor above 1 to get closer)
+ */
+pub trait CameraController {
+    fn name(&self) -> &'static str;
+    fn rotate(&mut self, cam: &mut Camera, yaw: f32, pitch: f32);
+    // forward, right and up in the camera frame
+    fn move_by(&mut self, cam: &mut Camera, forward: f32, right: f32, up: f32);
+    fn zoom(&mut self, cam: &mut Camera, factor: f32);
+    // slide the view along the screen axes
+    fn pan(&mut self, cam: &mut Camera, right: f32, up: f32);
+    // the camera has been moved to look at `target`
+    fn retarget(&mut self, _cam: &Camera, _target: Vec3) {}
+    // world units panned for a unit of input
+    fn pan_scale(&self) -> f32 {
+        1.0
+    }
+}
 
-// cam.move_in_circle(center, radius, speed, delta_time, height);
-pub fn cam_move_in_circle(mut cam: Camera, delta_time: f32) {
-    let center = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
-    let radius = 2.0;
-    let speed = 0.0005; // radians par seconde
-    let height = 0.0;
+/*
+ * First person camera moving on the horizontal plane
+ */
+#[derive(Debug, Default, Clone)]
+pub struct FreeFly;
 
-    // Calculer l'angle actuel en fonction du temps
-    let angle = (speed * delta_time) % (2.0 * f32::consts::PI);
+impl CameraController for FreeFly {
+    fn name(&self) -> &'static str {
+        "free-fly"
+    }
 
-    // Calculer la nouvelle position de la caméra
-    cam.position.x = center.x + radius * angle.cos();
-    cam.position.z = center.z + radius * angle.sin();
-    cam.position.y = center.y + height;
+    fn rotate(&mut self, cam: &mut Camera, yaw: f32, pitch: f32) {
+        cam.yaw += yaw;
+        cam.pitch = (cam.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+    }
 
-    // Garder la caméra focalisée sur le centre
-    cam.yaw += angle*delta_time;
-}
\ No newline at end of file
+    fn move_by(&mut self, cam: &mut Camera, forward: f32, right: f32, up: f32) {
+        cam.position += cam.get_forward_direction() * forward + cam.get_right_direction() * right;
+        cam.position.y += up;
+    }
+
+    fn zoom(&mut self, cam: &mut Camera, factor: f32) {
+        cam.projection.zoom(factor);
+    }
+
+    fn pan(&mut self, cam: &mut Camera, right: f32, up: f32) {
+        cam.position += cam.get_right_direction() * right + cam.get_up_direction() * up;
+    }
+}
+
+/*
+ * Camera turning around a target point at a given distance, always looking at it
+ */
+#[derive(Debug, Clone)]
+pub struct Orbit {
+    pub target: Vec3,
+    pub distance: f32,
+}
+
+impl Orbit {
+    /*
+     * Orbit around `target` from the current camera position
+     */
+    pub fn around(cam: &mut Camera, target: Vec3) -> Orbit {
+        let mut orbit = Orbit { target, distance: (target - cam.position).length().max(0.1) };
+        cam.look_at(target);
+        orbit.place(cam);
+        orbit
+    }
+
+    fn place(&mut self, cam: &mut Camera) {
+        cam.position = self.target - cam.get_look_at_direction() * self.distance;
+    }
+}
+
+impl CameraController for Orbit {
+    fn name(&self) -> &'static str {
+        "orbit"
+    }
+
+    fn rotate(&mut self, cam: &mut Camera, yaw: f32, pitch: f32) {
+        cam.yaw += yaw;
+        cam.pitch = (cam.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+        self.place(cam);
+    }
+
+    // going forward gets closer to the target, the other directions pan
+    fn move_by(&mut self, cam: &mut Camera, forward: f32, right: f32, up: f32) {
+        self.distance = (self.distance - forward).max(0.1);
+        self.pan(cam, right, up);
+    }
+
+    fn zoom(&mut self, cam: &mut Camera, factor: f32) {
+        match cam.projection {
+            Projection::Perspective { .. } => self.distance = (self.distance / factor).max(0.1),
+            Projection::Orthographic { .. } => cam.projection.zoom(factor),
+        }
+        self.place(cam);
+    }
+
+    fn pan(&mut self, cam: &mut Camera, right: f32, up: f32) {
+        self.target += cam.get_right_direction() * right + cam.get_up_direction() * up;
+        self.place(cam);
+    }
+
+    fn retarget(&mut self, cam: &Camera, target: Vec3) {
+        self.target = target;
+        self.distance = (target - cam.position).length().max(0.1);
+    }
+
+    // the farther the target, the faster the pan
+    fn pan_scale(&self) -> f32 {
+        self.distance
+    }
+}
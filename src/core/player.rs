@@ -1,69 +1,325 @@
-use std::io;
+use std::{io, time::{Duration, Instant}};
 
-use crossterm::event::{read, Event, KeyCode, KeyModifiers};
+use crossterm::event::{read, Event, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 
-use super::{engine::{Camera, LightSource}, math::triangle::Triangle3D};
+use super::{bindings::{Action, KeyBindings}, engine::{Camera, LightSource, RenderMode}, input::InputState, math::vector::Vec3, movements::{CameraController, FreeFly, Orbit}, scene::Scene};
 
+/*
+ * Mouse drags turn the camera (left button) or pan it (middle button), the wheel zooms,
+ * or changes the moving speed with ctrl. Sensitivities are given per cell crossed.
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct MouseSettings {
+  pub enabled: bool,
+  // radians per cell
+  pub sensitivity: f32,
+  // fraction of the distance to the target (orbit) or world units (free-fly) per cell
+  pub pan_sensitivity: f32,
+  pub invert_y: bool,
+  // zoom factor of a wheel step
+  pub wheel_zoom: f32
+}
 
-pub fn player_action (camera: &mut Camera, light_source: &mut LightSource, objects: &mut Vec<Triangle3D>, delta_time: f32) -> io::Result<()> {
-    let forward_direction = camera.get_forward_direction();
-    let right_direction = camera.get_right_direction();
+impl Default for MouseSettings {
+  fn default () -> Self {
+    MouseSettings { enabled: true, sensitivity: 0.02, pan_sensitivity: 0.02, invert_y: false, wheel_zoom: 1.1 }
+  }
+}
+
+/*
+ * Moves driven by the held keys: the velocity reaches `speed` (units per second)
+ * with `acceleration` (units per second squared), the camera turns at `turn_speed` (radians per second).
+ * Without key release events, a key is released after `key_timeout` without repeat.
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct MovementSettings {
+  pub speed: f32,
+  pub acceleration: f32,
+  pub turn_speed: f32,
+  pub key_timeout: Duration
+}
+
+impl Default for MovementSettings {
+  fn default () -> Self {
+    MovementSettings { speed: 5.0, acceleration: 20.0, turn_speed: 1.5, key_timeout: Duration::from_millis(500) }
+  }
+}
+
+/*
+ * How the player drives the engine
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Controls {
+  pub bindings: KeyBindings,
+  pub mouse: MouseSettings,
+  pub movement: MovementSettings
+}
+
+/*
+ * State of the interactive session besides the camera and the scene
+ */
+pub struct Player {
+  pub controller: Box<dyn CameraController>,
+  pub path_playing: bool,
+  // node framed by the key 'F', cycling through the nodes with a mesh
+  pub selection: Option<usize>,
+  // width divided by the height of the view
+  pub aspect: f32,
+  pub controls: Controls,
+  pub input: InputState,
+  // in the camera frame: right, up, forward
+  velocity: Vec3,
+  // button held and last cell of the current drag
+  drag: Option<(MouseButton, u16, u16)>,
+  // multiplies the moving steps
+  pub speed: f32,
+  // the overlay with the frame rate and the camera is drawn
+  pub hud: bool,
+  // the camera path and the moves are stopped, the frame is still drawn
+  pub paused: bool,
+  // set by the action Quit, the loop ends
+  pub quit: bool,
+  // new size of the terminal, in cells, until the loop applies it
+  pub resize: Option<(usize, usize)>
+}
+
+impl Player {
+  pub fn new (aspect: f32, controls: Controls, release_events: bool) -> Player {
+    let input = InputState::new(release_events, controls.movement.key_timeout);
+    Player {
+      controller: Box::new(FreeFly),
+      path_playing: false,
+      selection: None,
+      aspect,
+      controls,
+      input,
+      velocity: Vec3::new(0.0, 0.0, 0.0),
+      drag: None,
+      speed: 1.0,
+      hud: false,
+      paused: false,
+      quit: false,
+      resize: None
+    }
+  }
+
+  /*
+   * Move and turn the camera, and turn the first light, with the held keys for `delta_time` seconds
+   */
+  pub fn update (&mut self, camera: &mut Camera, lights: &mut [LightSource], delta_time: f32) {
+    self.input.update(Instant::now());
+    let mut direction = Vec3::new(0.0, 0.0, 0.0);
+    let (mut yaw, mut pitch) = (0.0, 0.0);
+    let mut light_turns = false;
+    for action in self.input.held().filter_map(|key| self.controls.bindings.action_of(key)) {
+      match action {
+        Action::MoveForward => direction.z += 1.0,
+        Action::MoveBackward => direction.z -= 1.0,
+        Action::MoveRight => direction.x += 1.0,
+        Action::MoveLeft => direction.x -= 1.0,
+        Action::MoveUp => direction.y += 1.0,
+        Action::MoveDown => direction.y -= 1.0,
+        Action::LookLeft => yaw += 1.0,
+        Action::LookRight => yaw -= 1.0,
+        Action::LookUp => pitch += 1.0,
+        Action::LookDown => pitch -= 1.0,
+        Action::MoveLight => light_turns = true,
+        _ => {}
+      }
+    }
+    if let Some(light_source) = lights.first_mut().filter(|_| light_turns) {
+      light_source.move_in_circle(delta_time);
+    }
+    let movement = self.controls.movement;
+    if yaw != 0.0 || pitch != 0.0 {
+      let angle = movement.turn_speed * delta_time;
+      self.controller.rotate(camera, yaw * angle, pitch * angle);
+    }
+
+    // the diagonals are not faster
+    let target = if direction.length2() > 0.0 {
+      direction.normalize() * (movement.speed * self.speed)
+    } else {
+      direction
+    };
+    let change = target - self.velocity;
+    let max_change = movement.acceleration * self.speed * delta_time;
+    self.velocity += if change.length() > max_change { change.normalize() * max_change } else { change };
+    if self.velocity.length2() > 0.0 {
+      let step = self.velocity * delta_time;
+      self.controller.move_by(camera, step.z, step.x, step.y);
+    }
+  }
+
+  /*
+   * Place the camera to see the whole scene, or only the selected node
+   */
+  pub fn frame (&mut self, camera: &mut Camera, scene: &Scene, selection: Option<usize>) {
+    let bounds = match selection {
+      Some(index) => scene.node_bounds(index),
+      None => scene.bounds()
+    };
+    if let Some((min, max)) = bounds {
+      camera.frame(min, max, self.aspect);
+      self.controller.retarget(camera, 0.5 * (min + max));
+    }
+  }
+
+  fn select_next (&mut self, scene: &Scene) {
+    let start = self.selection.map_or(0, |index| index + 1);
+    self.selection = (start..scene.nodes.len()).chain(0..start)
+      .find(|&index| scene.nodes[index].mesh.is_some());
+  }
+}
+
+pub fn player_action (camera: &mut Camera, player: &mut Player, scene: &mut Scene, render_mode: &mut RenderMode) -> io::Result<()> {
     match read()? {
       Event::Key(event) => {
-        match event.code {
-          KeyCode::Down => {
-            if camera.pitch > -1.57 {
-              camera.pitch -= 0.01*delta_time;
-            }
-          },
-          KeyCode::Up => {
-            if camera.pitch < 1.57 {
-              camera.pitch += 0.01*delta_time;
-            }
-          },
-          KeyCode::Left => {
-            camera.yaw += 0.01*delta_time;
-          },
-          KeyCode::Right => {
-            camera.yaw -= 0.01*delta_time;
-          },
-          KeyCode::Char('z') => {
-            camera.position += forward_direction*0.01*delta_time;
-          },
-          KeyCode::Char('s') => {
-            camera.position -= forward_direction*0.01*delta_time;
-          },
-          KeyCode::Char('q') => {
-            camera.position -= right_direction*0.01*delta_time;
-          },
-          KeyCode::Char('d') => {
-            camera.position += right_direction*0.01*delta_time;
-          },
-          KeyCode::Char(' ') => {
-            if event.modifiers == KeyModifiers::CONTROL {
-              camera.position.y -= 0.01*delta_time;
-            } else {
-              camera.position.y += 0.01*delta_time;
-            }
-          },
-          KeyCode::Char('r') => {
-            objects.iter_mut().for_each(|o| *o = o.rotation_y(0.2));
-          },
-          KeyCode::Char('R') => {
-            objects.iter_mut().for_each(|o| *o = o.rotation_y(-0.2));
-          },
-          KeyCode::Char('t') => {
-            light_source.move_in_circle(delta_time);
-        },
-          _ => {}
+        player.input.handle(&event, Instant::now());
+        // the held keys are applied by Player::update
+        let action = player.controls.bindings.action(&event).filter(|action| !action.is_continuous());
+        if let Some(action) = action {
+          match event.kind {
+            KeyEventKind::Press => perform(action, camera, player, scene, render_mode),
+            KeyEventKind::Repeat if action.repeats() => perform(action, camera, player, scene, render_mode),
+            _ => {}
+          }
         }
       },
-      // Event::Mouse(event) => {},
-      // Event::Paste(data) => {},
-      // Event::Resize(width, height) => {},
-      // Event::FocusGained => {},
-      // Event::FocusLost => {},
-      _ => {},
+      // a release could be lost while the terminal has not the focus
+      Event::FocusLost => player.input.clear(),
+      Event::Mouse(event) if player.controls.mouse.enabled => mouse_action(&event, camera, player),
+      Event::Resize(width, height) => player.resize = Some((width as usize, height as usize)),
+      _ => {}
     }
     Ok(())
-  }
\ No newline at end of file
+  }
+
+fn mouse_action (event: &MouseEvent, camera: &mut Camera, player: &mut Player) {
+    let mouse = player.controls.mouse;
+    match event.kind {
+      MouseEventKind::Down(button) => {
+        player.drag = Some((button, event.column, event.row));
+      },
+      MouseEventKind::Up(_) => {
+        player.drag = None;
+      },
+      MouseEventKind::Drag(button) => {
+        let Some((_, column, row)) = player.drag.filter(|(drag_button, _, _)| *drag_button == button) else {
+          player.drag = Some((button, event.column, event.row));
+          return;
+        };
+        let dx = event.column as f32 - column as f32;
+        let dy = event.row as f32 - row as f32;
+        player.drag = Some((button, event.column, event.row));
+        let dy = if mouse.invert_y { -dy } else { dy };
+        match button {
+          MouseButton::Left => player.controller.rotate(camera, -dx * mouse.sensitivity, -dy * mouse.sensitivity),
+          MouseButton::Middle => {
+            let scale = mouse.pan_sensitivity * player.controller.pan_scale();
+            player.controller.pan(camera, -dx * scale, dy * scale);
+          },
+          MouseButton::Right => {}
+        }
+      },
+      MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+        let factor = if event.kind == MouseEventKind::ScrollUp { mouse.wheel_zoom } else { 1.0 / mouse.wheel_zoom };
+        if event.modifiers.contains(KeyModifiers::CONTROL) {
+          player.speed = (player.speed * factor).clamp(0.01, 100.0);
+        } else {
+          player.controller.zoom(camera, factor);
+        }
+      },
+      _ => {}
+    }
+  }
+
+pub fn perform (action: Action, camera: &mut Camera, player: &mut Player, scene: &mut Scene, render_mode: &mut RenderMode) {
+    let controller = &mut player.controller;
+    match action {
+      // driven by the held keys
+      Action::LookDown | Action::LookUp | Action::LookLeft | Action::LookRight
+      | Action::MoveForward | Action::MoveBackward | Action::MoveLeft | Action::MoveRight | Action::MoveUp | Action::MoveDown
+      | Action::MoveLight => {},
+      Action::ZoomIn => controller.zoom(camera, 1.1),
+      Action::ZoomOut => controller.zoom(camera, 1.0 / 1.1),
+      Action::SwitchController => {
+        *controller = switch_controller(controller.as_ref(), camera, scene);
+      },
+      Action::RotateModel | Action::RotateModelBack => {
+        let angle = if action == Action::RotateModel { 0.2 } else { -0.2 };
+        scene.nodes.iter_mut()
+          .filter(|node| node.parent.is_none())
+          .for_each(|node| node.transform.rotation.y += angle);
+      },
+      Action::ToggleCameraPath => {
+        player.path_playing = !player.path_playing && scene.camera_path.is_some();
+      },
+      Action::FrameAll => {
+        player.selection = None;
+        player.frame(camera, scene, None);
+      },
+      Action::FrameNext => {
+        player.select_next(scene);
+        player.frame(camera, scene, player.selection);
+      },
+      Action::CycleRenderMode => {
+        *render_mode = render_mode.next();
+      },
+      Action::ToggleWireframe => {
+        *render_mode = if *render_mode == RenderMode::Wireframe { RenderMode::Filled } else { RenderMode::Wireframe };
+      },
+      Action::ToggleHud => {
+        player.hud = !player.hud;
+      },
+      Action::Pause => {
+        player.paused = !player.paused;
+      },
+      Action::Quit => {
+        player.quit = true;
+      }
+    }
+  }
+
+/*
+ * Free-fly becomes an orbit around the centre of the scene, or a point ahead without scene
+ */
+fn switch_controller (controller: &dyn CameraController, camera: &mut Camera, scene: &Scene) -> Box<dyn CameraController> {
+  if controller.name() == "orbit" {
+    return Box::new(FreeFly);
+  }
+  let target = match scene.bounds() {
+    Some((min, max)) => 0.5 * (min + max),
+    None => camera.position + camera.get_look_at_direction() * 2.0
+  };
+  Box::new(Orbit::around(camera, target))
+}
+
+#[cfg(test)]
+mod tests {
+  use crossterm::event::{KeyCode, KeyEvent};
+
+  use super::*;
+
+  #[test]
+  fn held_light_key_turns_the_light_with_the_time () {
+    let mut player = Player::new(1.0, Controls::default(), true);
+    let mut camera = Camera::default();
+    let mut lights = [LightSource::at(&Vec3::new(2.0, 5.0, 0.0))];
+    player.update(&mut camera, &mut lights, 0.5);
+    assert_eq!((lights[0].position.x, lights[0].position.z), (2.0, 0.0));
+
+    player.input.handle(&KeyEvent::new(KeyCode::Char('t'), KeyModifiers::NONE), Instant::now());
+    // the same angle in one step or in ten
+    player.update(&mut camera, &mut lights, 0.5);
+    let mut stepped = [LightSource::at(&Vec3::new(2.0, 5.0, 0.0))];
+    for _ in 0..10 {
+      player.update(&mut camera, &mut stepped, 0.05);
+    }
+    let angle = |light: &LightSource| light.position.z.atan2(light.position.x);
+    assert!((angle(&lights[0]) - 0.75).abs() < 1e-4);
+    assert!((angle(&stepped[0]) - 0.75).abs() < 1e-4);
+    assert!((lights[0].position.y - 5.0).abs() < 1e-6);
+    assert!((Vec3::new(lights[0].position.x, 0.0, lights[0].position.z).length() - 2.0).abs() < 1e-4);
+  }
+}
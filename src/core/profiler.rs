@@ -0,0 +1,199 @@
+use std::{fmt::Write as _, fs, io, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+// spans kept for the trace file, the summary goes on beyond
+const MAX_SPANS: usize = 1_000_000;
+
+/*
+ * Stage timed once, `start` from the creation of the profiler
+ */
+#[derive(Debug, Copy, Clone)]
+struct Span {
+  name: &'static str,
+  start: Duration,
+  duration: Duration,
+  frame: usize
+}
+
+#[derive(Debug, Default)]
+struct ProfileState {
+  frame: usize,
+  spans: Vec<Span>,
+  // time of each stage in the current frame, a stage can run several times
+  frame_times: Vec<(&'static str, Duration)>,
+  // the same for the last finished frame, shown by the overlay
+  last_frame: Vec<(&'static str, Duration)>,
+  // time of each stage in every finished frame, in the order the stages first ran
+  stages: Vec<(&'static str, Vec<Duration>)>
+}
+
+/*
+ * Times of one stage over the frames
+ */
+#[derive(Debug, Clone)]
+pub struct StageSummary {
+  pub name: &'static str,
+  pub frames: usize,
+  pub min: Duration,
+  pub avg: Duration,
+  pub p99: Duration,
+  pub max: Duration
+}
+
+/*
+ * Timers around the stages of the engine, summed per frame. Disabled, only the times of the last
+ * frame are kept, for the overlay, without the spans nor the summary.
+ */
+#[derive(Debug)]
+pub struct Profiler {
+  pub enabled: bool,
+  // Chrome trace-event file written by `finish`
+  pub trace_path: Option<PathBuf>,
+  origin: Instant,
+  state: Mutex<ProfileState>
+}
+
+impl Default for Profiler {
+  fn default () -> Self {
+    Profiler::new(false, None)
+  }
+}
+
+/*
+ * Time from its creation to its drop, recorded in the profiler
+ */
+pub struct Scope {
+  profiler: Arc<Profiler>,
+  name: &'static str,
+  start: Instant
+}
+
+impl Drop for Scope {
+  fn drop (&mut self) {
+    self.profiler.record(self.name, self.start, Instant::now());
+  }
+}
+
+impl Profiler {
+  pub fn new (enabled: bool, trace_path: Option<PathBuf>) -> Profiler {
+    Profiler { enabled, trace_path, origin: Instant::now(), state: Mutex::new(ProfileState::default()) }
+  }
+
+  /*
+   * Timer of the stage `name` until the returned scope is dropped,
+   * the scope keeps its own handle and leaves the engine free to borrow
+   */
+  pub fn scope (profiler: &Arc<Profiler>, name: &'static str) -> Scope {
+    Scope { profiler: Arc::clone(profiler), name, start: Instant::now() }
+  }
+
+  pub fn record (&self, name: &'static str, start: Instant, end: Instant) {
+    let Ok(mut state) = self.state.lock() else {
+      return;
+    };
+    let duration = end.duration_since(start);
+    let frame = state.frame;
+    if self.enabled && state.spans.len() < MAX_SPANS {
+      state.spans.push(Span { name, start: start.duration_since(self.origin), duration, frame });
+    }
+    match state.frame_times.iter_mut().find(|(stage, _)| *stage == name) {
+      Some((_, time)) => *time += duration,
+      None => state.frame_times.push((name, duration))
+    }
+  }
+
+  /*
+   * Close the current frame, its stage times join the summary
+   */
+  pub fn end_frame (&self) {
+    let Ok(mut state) = self.state.lock() else {
+      return;
+    };
+    state.last_frame = std::mem::take(&mut state.frame_times);
+    if self.enabled {
+      let ProfileState { stages, last_frame, .. } = &mut *state;
+      for &(name, time) in last_frame.iter() {
+        match stages.iter_mut().find(|(stage, _)| *stage == name) {
+          Some((_, times)) => times.push(time),
+          None => stages.push((name, vec![time]))
+        }
+      }
+    }
+    state.frame += 1;
+  }
+
+  /*
+   * Time of each stage in the last finished frame, in the order they ran
+   */
+  pub fn last_frame (&self) -> Vec<(&'static str, Duration)> {
+    self.state.lock().map_or(vec![], |state| state.last_frame.clone())
+  }
+
+  pub fn summary (&self) -> Vec<StageSummary> {
+    let Ok(state) = self.state.lock() else {
+      return vec![];
+    };
+    state.stages.iter()
+      .map(|(name, times)| {
+        let mut sorted = times.clone();
+        sorted.sort();
+        // nearest rank
+        let p99_index = ((sorted.len() as f64 * 0.99).ceil() as usize).clamp(1, sorted.len()) - 1;
+        StageSummary {
+          name,
+          frames: sorted.len(),
+          min: sorted[0],
+          avg: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+          p99: sorted[p99_index],
+          max: sorted[sorted.len() - 1]
+        }
+      })
+      .collect()
+  }
+
+  /*
+   * Table of the stage times per frame, in milliseconds
+   */
+  pub fn report (&self) -> String {
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mut report = format!("{:<12} {:>8} {:>10} {:>10} {:>10} {:>10}\n", "stage", "frames", "min (ms)", "avg (ms)", "p99 (ms)", "max (ms)");
+    for stage in self.summary() {
+      let _ = writeln!(report, "{:<12} {:>8} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+        stage.name, stage.frames, ms(stage.min), ms(stage.avg), ms(stage.p99), ms(stage.max));
+    }
+    report
+  }
+
+  /*
+   * Spans as Chrome trace events ("X" complete events in microseconds), opened by chrome://tracing or Perfetto
+   */
+  pub fn chrome_trace (&self) -> String {
+    let Ok(state) = self.state.lock() else {
+      return String::from("[]");
+    };
+    let events: Vec<String> = state.spans.iter()
+      .map(|span| format!(
+        "{{\"name\":\"{}\",\"cat\":\"engine\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":1,\"args\":{{\"frame\":{}}}}}",
+        span.name, span.start.as_secs_f64() * 1e6, span.duration.as_secs_f64() * 1e6, span.frame))
+      .collect();
+    format!("{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ms\"}}\n", events.join(",\n"))
+  }
+
+  pub fn write_chrome_trace (&self, path: &Path) -> io::Result<()> {
+    fs::write(path, self.chrome_trace())
+  }
+
+  /*
+   * Print the summary and write the trace file, when profiling
+   */
+  pub fn finish (&self) -> io::Result<()> {
+    if !self.enabled {
+      return Ok(());
+    }
+    print!("{}", self.report());
+    if let Some(path) = &self.trace_path {
+      self.write_chrome_trace(path)?;
+      println!("Trace written to {:?}", path);
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,115 @@
+use std::{fmt, str::FromStr};
+
+use super::math::vector::{Vec2, Vec3};
+
+const DEFAULT_FOV: f32 = 90.0;
+const DEFAULT_VIEW_HEIGHT: f32 = 10.0;
+
+/*
+ * Perspective with a vertical field of view in degrees,
+ * or orthographic showing `height` world units from the bottom to the top of the screen
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Projection {
+  Perspective { fov: f32 },
+  Orthographic { height: f32 }
+}
+
+impl Default for Projection {
+  fn default () -> Self {
+    Projection::Perspective { fov: DEFAULT_FOV }
+  }
+}
+
+/*
+ * "perspective", "orthographic", optionally followed by the fov or the view height: "perspective:60"
+ */
+impl FromStr for Projection {
+  type Err = String;
+
+  fn from_str (s: &str) -> Result<Self, Self::Err> {
+    let (kind, value) = match s.split_once(':') {
+      Some((kind, value)) => {
+        let value = value.trim().parse::<f32>().map_err(|e| format!("Invalid projection value '{}': {}", value, e))?;
+        (kind, Some(value))
+      },
+      None => (s, None)
+    };
+    match kind.trim().to_lowercase().as_str() {
+      "perspective" => Projection::perspective(value.unwrap_or(DEFAULT_FOV)),
+      "orthographic" => {
+        let height = value.unwrap_or(DEFAULT_VIEW_HEIGHT);
+        if height <= 0.0 {
+          return Err(format!("The view height must be positive, not {}", height));
+        }
+        Ok(Projection::Orthographic { height })
+      },
+      _ => Err(format!("Unknown projection '{}', expected perspective or orthographic", s))
+    }
+  }
+}
+
+impl fmt::Display for Projection {
+  fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Projection::Perspective { fov } => write!(f, "perspective:{}", fov),
+      Projection::Orthographic { height } => write!(f, "orthographic:{}", height)
+    }
+  }
+}
+
+impl Projection {
+  /*
+   * Perspective with a field of view strictly between 0 and 180 degrees
+   */
+  pub fn perspective (fov: f32) -> Result<Projection, String> {
+    if !(fov > 0.0 && fov < 180.0) {
+      return Err(format!("The field of view must be between 0 and 180 degrees, not {}", fov));
+    }
+    Ok(Projection::Perspective { fov })
+  }
+
+  /*
+   * Same perspective with another field of view, an orthographic projection has none
+   */
+  pub fn with_fov (self, fov: f32) -> Result<Projection, String> {
+    match self {
+      Projection::Perspective { .. } => Projection::perspective(fov),
+      Projection::Orthographic { .. } => Err(format!("The field of view {} needs a perspective, not the projection {}", fov, self))
+    }
+  }
+
+  pub fn is_perspective (self) -> bool {
+    matches!(self, Projection::Perspective { .. })
+  }
+
+  /*
+   * Half width and half height of the visible area at the distance `z`,
+   * `aspect` is the width of the view divided by its height
+   */
+  pub fn half_extent (self, z: f32, aspect: f32) -> (f32, f32) {
+    let half_height = match self {
+      Projection::Perspective { fov } => (fov.to_radians() / 2.0).tan() * z,
+      Projection::Orthographic { height } => height / 2.0
+    };
+    (half_height * aspect, half_height)
+  }
+
+  /*
+   * Point of the view space in normalized device coordinates, [-1, 1] covering the screen
+   */
+  pub fn project (self, v: Vec3, aspect: f32) -> Vec2 {
+    let (half_width, half_height) = self.half_extent(v.z, aspect);
+    Vec2::new(v.x / half_width, v.y / half_height)
+  }
+
+  /*
+   * Magnify the view by `factor` (zoom out below 1)
+   */
+  pub fn zoom (&mut self, factor: f32) {
+    match self {
+      Projection::Perspective { fov } => *fov = (*fov / factor).clamp(1.0, 179.0),
+      Projection::Orthographic { height } => *height = (*height / factor).max(0.001)
+    }
+  }
+}
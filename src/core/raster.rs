@@ -0,0 +1,395 @@
+use super::math::vector::{Vec2, Vec3};
+
+/*
+ * Per-vertex attribute interpolated across a triangle (depth, UV, normal, colour...)
+ */
+pub trait Varying: Copy {
+  fn interpolate (values: [Self; 3], weights: [f32; 3]) -> Self;
+}
+
+impl Varying for () {
+  fn interpolate (_values: [Self; 3], _weights: [f32; 3]) -> Self {}
+}
+
+impl Varying for f32 {
+  fn interpolate (values: [Self; 3], weights: [f32; 3]) -> Self {
+    values[0] * weights[0] + values[1] * weights[1] + values[2] * weights[2]
+  }
+}
+
+impl Varying for Vec2 {
+  fn interpolate (values: [Self; 3], weights: [f32; 3]) -> Self {
+    weights[0] * values[0] + weights[1] * values[1] + weights[2] * values[2]
+  }
+}
+
+impl Varying for Vec3 {
+  fn interpolate (values: [Self; 3], weights: [f32; 3]) -> Self {
+    weights[0] * values[0] + weights[1] * values[1] + weights[2] * values[2]
+  }
+}
+
+/*
+ * Vertex already projected on the screen.
+ * `z` is the distance along the view axis, giving the depth,
+ * `inv_w` weights the interpolation: 1/z with a perspective, 1 with an orthographic projection.
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct RasterVertex<V: Varying> {
+  pub position: Vec2,
+  pub inv_w: f32,
+  pub z: f32,
+  pub varying: V
+}
+
+impl<V: Varying> RasterVertex<V> {
+  pub fn new (position: Vec2, z: f32, varying: V) -> RasterVertex<V> {
+    RasterVertex { position, inv_w: 1.0 / z, z, varying }
+  }
+
+  pub fn orthographic (position: Vec2, z: f32, varying: V) -> RasterVertex<V> {
+    RasterVertex { position, inv_w: 1.0, z, varying }
+  }
+
+  pub fn with_varying<W: Varying> (&self, varying: W) -> RasterVertex<W> {
+    RasterVertex { position: self.position, inv_w: self.inv_w, z: self.z, varying }
+  }
+}
+
+/*
+ * Cell covered by a triangle.
+ * `barycentric` are the perspective-corrected weights of (v1, v2, v3),
+ * `depth` is the interpolated 1/z (greater is nearer).
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct Fragment<V: Varying> {
+  pub x: usize,
+  pub y: usize,
+  pub barycentric: [f32; 3],
+  pub depth: f32,
+  pub varying: V
+}
+
+/*
+ * Area of the screen in cells, `x1` and `y1` excluded
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+  pub x0: usize,
+  pub y0: usize,
+  pub x1: usize,
+  pub y1: usize
+}
+
+impl Rect {
+  pub fn new (x0: usize, y0: usize, x1: usize, y1: usize) -> Rect {
+    Rect { x0, y0, x1, y1 }
+  }
+  pub fn screen (width: usize, height: usize) -> Rect {
+    Rect { x0: 0, y0: 0, x1: width, y1: height }
+  }
+  pub fn is_empty (&self) -> bool {
+    self.x0 >= self.x1 || self.y0 >= self.y1
+  }
+}
+
+/*
+ * Cells possibly covered by the triangle, clamped to `clip`
+ */
+pub fn bounding_box<V: Varying> (vertices: &[RasterVertex<V>; 3], clip: &Rect) -> Rect {
+  let (p1, p2, p3) = (vertices[0].position, vertices[1].position, vertices[2].position);
+  let to_cell = |value: f32, min: usize, max: usize| (value.max(min as f32).min(max as f32)) as usize;
+  Rect {
+    x0: to_cell(p1.x.min(p2.x).min(p3.x).floor(), clip.x0, clip.x1),
+    y0: to_cell(p1.y.min(p2.y).min(p3.y).floor(), clip.y0, clip.y1),
+    x1: to_cell(p1.x.max(p2.x).max(p3.x).ceil(), clip.x0, clip.x1),
+    y1: to_cell(p1.y.max(p2.y).max(p3.y).ceil(), clip.y0, clip.y1)
+  }
+}
+
+/*
+ * Vertices are snapped on a grid of 1/SUBPIXEL cell, so edge functions are
+ * computed and stepped with integers: no rounding error, no crack between triangles.
+ */
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL: i64 = 1 << SUBPIXEL_BITS;
+// beyond this size (in cells) the products of the edge functions could overflow
+const MAX_COORDINATE: f32 = (1 << 22) as f32;
+// cells evaluated together in the inner loop
+const BLOCK_WIDTH: usize = 4;
+
+/*
+ * Edge function of the oriented edge (a, b): e(x, y) = a_x * x + b_y * y + c,
+ * evaluated at cell centres and stepped of one cell with `step_x` / `step_y`
+ */
+#[derive(Debug, Copy, Clone)]
+struct Edge {
+  step_x: i64,
+  step_y: i64,
+  // -1 on edges which are not top-left, so that `e + bias >= 0` applies the fill rule
+  bias: i64
+}
+
+impl Edge {
+  fn new (a: (i64, i64), b: (i64, i64)) -> Edge {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    Edge {
+      step_x: -dy * SUBPIXEL,
+      step_y: dx * SUBPIXEL,
+      bias: if is_top_left(dx, dy) { 0 } else { -1 }
+    }
+  }
+
+  fn at (a: (i64, i64), b: (i64, i64), p: (i64, i64)) -> i64 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+  }
+}
+
+/*
+ * Top-left fill rule: a cell lying exactly on an edge belongs to the triangle
+ * only if the edge is a top or a left one, so shared edges are drawn once.
+ */
+fn is_top_left (dx: i64, dy: i64) -> bool {
+  (dy == 0 && dx > 0) || dy < 0
+}
+
+fn to_fixed (position: Vec2) -> (i64, i64) {
+  ((position.x * SUBPIXEL as f32).round() as i64, (position.y * SUBPIXEL as f32).round() as i64)
+}
+
+/*
+ * Call `fragment` for every cell of `clip` whose centre is covered by the triangle.
+ * Both windings are accepted, degenerated triangles are ignored.
+ */
+pub fn rasterize_triangle<V: Varying, F: FnMut(&Fragment<V>)> (vertices: &[RasterVertex<V>; 3], clip: &Rect, mut fragment: F) {
+  let out_of_range = |v: &RasterVertex<V>| !(v.position.x.abs() < MAX_COORDINATE && v.position.y.abs() < MAX_COORDINATE);
+  if vertices.iter().any(out_of_range) {
+    return;
+  }
+  let bounds = bounding_box(vertices, clip);
+  if bounds.is_empty() {
+    return;
+  }
+
+  let [mut v1, mut v2, v3] = *vertices;
+  let (mut p1, mut p2, p3) = (to_fixed(v1.position), to_fixed(v2.position), to_fixed(v3.position));
+  let mut area = Edge::at(p1, p2, p3);
+  if area == 0 {
+    return;
+  }
+  // Keep a single winding so that "inside" always means positive edge functions
+  let swapped = area < 0;
+  if swapped {
+    std::mem::swap(&mut v1, &mut v2);
+    std::mem::swap(&mut p1, &mut p2);
+    area = -area;
+  }
+
+  let edges = [Edge::new(p2, p3), Edge::new(p3, p1), Edge::new(p1, p2)];
+  let origin = (bounds.x0 as i64 * SUBPIXEL + SUBPIXEL / 2, bounds.y0 as i64 * SUBPIXEL + SUBPIXEL / 2);
+  let mut row = [
+    Edge::at(p2, p3, origin) + edges[0].bias,
+    Edge::at(p3, p1, origin) + edges[1].bias,
+    Edge::at(p1, p2, origin) + edges[2].bias,
+  ];
+  let inv_area = 1.0 / area as f32;
+  let inv_w = [v1.inv_w, v2.inv_w, v3.inv_w];
+  let z = [v1.z, v2.z, v3.z];
+  let varyings = [v1.varying, v2.varying, v3.varying];
+
+  for y in bounds.y0..bounds.y1 {
+    let mut block = row;
+    let mut x = bounds.x0;
+    while x < bounds.x1 {
+      let lanes = BLOCK_WIDTH.min(bounds.x1 - x);
+      // edge functions of the whole block, written to be vectorized
+      let mut mask = [false; BLOCK_WIDTH];
+      for (lane, inside) in mask.iter_mut().enumerate() {
+        let lane_step = lane as i64;
+        *inside = (block[0] + lane_step * edges[0].step_x)
+          | (block[1] + lane_step * edges[1].step_x)
+          | (block[2] + lane_step * edges[2].step_x) >= 0;
+      }
+      for (lane, _) in mask[..lanes].iter().enumerate().filter(|(_, inside)| **inside) {
+        let lane_step = lane as i64;
+        let weights = [
+          (block[0] + lane_step * edges[0].step_x - edges[0].bias) as f32 * inv_area,
+          (block[1] + lane_step * edges[1].step_x - edges[1].bias) as f32 * inv_area,
+          (block[2] + lane_step * edges[2].step_x - edges[2].bias) as f32 * inv_area,
+        ];
+        let w = weights[0] * inv_w[0] + weights[1] * inv_w[1] + weights[2] * inv_w[2];
+        let mut weights = [weights[0] * inv_w[0] / w, weights[1] * inv_w[1] / w, weights[2] * inv_w[2] / w];
+        let depth = 1.0 / (weights[0] * z[0] + weights[1] * z[1] + weights[2] * z[2]);
+        let varying = V::interpolate(varyings, weights);
+        if swapped {
+          weights.swap(0, 1);
+        }
+        fragment(&Fragment { x: x + lane, y, barycentric: weights, depth, varying });
+      }
+      for (e, edge) in block.iter_mut().zip(&edges) {
+        *e += BLOCK_WIDTH as i64 * edge.step_x;
+      }
+      x += BLOCK_WIDTH;
+    }
+    for (e, edge) in row.iter_mut().zip(&edges) {
+      *e += edge.step_y;
+    }
+  }
+}
+
+/*
+ * Part of the segment [a, b] inside `clip` (Liang-Barsky), as parameters of the segment
+ */
+fn clip_segment (a: Vec2, b: Vec2, clip: &Rect) -> Option<(f32, f32)> {
+  let (dx, dy) = (b.x - a.x, b.y - a.y);
+  let (mut t0, mut t1) = (0.0_f32, 1.0_f32);
+  let bounds = [
+    (-dx, a.x - clip.x0 as f32),
+    (dx, clip.x1 as f32 - a.x),
+    (-dy, a.y - clip.y0 as f32),
+    (dy, clip.y1 as f32 - a.y),
+  ];
+  for (p, q) in bounds {
+    if p == 0.0 {
+      if q < 0.0 {
+        return None;
+      }
+    } else {
+      let t = q / p;
+      if p < 0.0 {
+        t0 = t0.max(t);
+      } else {
+        t1 = t1.min(t);
+      }
+    }
+  }
+  (t0 <= t1).then_some((t0, t1))
+}
+
+/*
+ * Char drawing a line with this slope (in cells, y going down)
+ */
+pub fn line_char (a: Vec2, b: Vec2) -> char {
+  let (dx, dy) = (b.x - a.x, b.y - a.y);
+  if dy.abs() * 2.0 < dx.abs() {
+    '-'
+  } else if dx.abs() * 2.0 < dy.abs() {
+    '|'
+  } else if (dx > 0.0) == (dy > 0.0) {
+    '\\'
+  } else {
+    '/'
+  }
+}
+
+/*
+ * Call `fragment` with (x, y, depth) for every cell of `clip` crossed by the segment,
+ * the depth being 1/z like for the triangles
+ */
+pub fn rasterize_line<F: FnMut(usize, usize, f32)> (a: &RasterVertex<()>, b: &RasterVertex<()>, clip: &Rect, mut fragment: F) {
+  let (pa, pb) = (a.position, b.position);
+  if !(pa.x.is_finite() && pa.y.is_finite() && pb.x.is_finite() && pb.y.is_finite()) || clip.is_empty() {
+    return;
+  }
+  let Some((t0, t1)) = clip_segment(pa, pb, clip) else {
+    return;
+  };
+  let start = pa + t0 * (pb - pa);
+  let end = pa + t1 * (pb - pa);
+  let steps = (end.x - start.x).abs().max((end.y - start.y).abs()).ceil().max(1.0) as usize;
+  for step in 0..=steps {
+    let t = t0 + (t1 - t0) * step as f32 / steps as f32;
+    let point = pa + t * (pb - pa);
+    let (x, y) = (point.x.floor(), point.y.floor());
+    if x < clip.x0 as f32 || y < clip.y0 as f32 || x >= clip.x1 as f32 || y >= clip.y1 as f32 {
+      continue;
+    }
+    // perspective-corrected position along the segment
+    let s = t * b.inv_w / ((1.0 - t) * a.inv_w + t * b.inv_w);
+    fragment(x as usize, y as usize, 1.0 / (a.z + s * (b.z - a.z)));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn vertex (x: f32, y: f32) -> RasterVertex<()> {
+    RasterVertex::new(Vec2::new(x, y), 1.0, ())
+  }
+
+  // number of times each cell is drawn
+  fn coverage (triangles: &[[(f32, f32); 3]], width: usize, height: usize) -> Vec<u32> {
+    let mut counts = vec![0; width * height];
+    for triangle in triangles {
+      let vertices = triangle.map(|(x, y)| vertex(x, y));
+      rasterize_triangle(&vertices, &Rect::screen(width, height), |fragment| counts[fragment.y * width + fragment.x] += 1);
+    }
+    counts
+  }
+
+  #[test]
+  fn quad_split_on_its_diagonal_is_drawn_once () {
+    // the diagonal goes through the cell centres, both windings
+    let quads = [
+      [[(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)], [(10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]],
+      [[(0.0, 0.0), (0.0, 10.0), (10.0, 0.0)], [(10.0, 0.0), (0.0, 10.0), (10.0, 10.0)]],
+    ];
+    for quad in quads {
+      let counts = coverage(&quad, 12, 12);
+      for y in 0..12 {
+        for x in 0..12 {
+          let expected = if x < 10 && y < 10 { 1 } else { 0 };
+          assert_eq!(counts[y * 12 + x], expected, "cell ({}, {})", x, y);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn triangles_sharing_edges_cover_each_cell_once () {
+    // fan around a cell centre, the edges crossing cells at any slope
+    let center = (7.5, 6.5);
+    let outline = [(1.0, 1.0), (9.3, 0.2), (14.5, 5.5), (12.0, 13.7), (4.5, 12.5), (0.5, 7.0)];
+    let triangles: Vec<[(f32, f32); 3]> = (0..outline.len())
+      .map(|i| [center, outline[i], outline[(i + 1) % outline.len()]])
+      .collect();
+    let counts = coverage(&triangles, 16, 16);
+    assert!(counts.iter().all(|&count| count <= 1));
+    // the cells whose centre is well inside the outline
+    let inside = |x: f32, y: f32| (0..outline.len()).all(|i| {
+      let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+      (b.0 - a.0) * (y - a.1) - (b.1 - a.1) * (x - a.0) > 0.01
+    });
+    let mut checked = 0;
+    for y in 0..16 {
+      for x in 0..16 {
+        if inside(x as f32 + 0.5, y as f32 + 0.5) {
+          assert_eq!(counts[y * 16 + x], 1, "cell ({}, {})", x, y);
+          checked += 1;
+        }
+      }
+    }
+    assert!(checked > 100);
+  }
+
+  #[test]
+  fn weights_are_perspective_correct () {
+    // screen positions of points at different distances: (X, Y) = (x, y) * z
+    let corners = [(1.0, 1.0, 1.0), (15.0, 2.0, 2.0), (4.0, 13.0, 4.0)];
+    let vertices = corners.map(|(x, y, z)| RasterVertex::new(Vec2::new(x, y), z, Vec2::new(x * z, y * z)));
+    let mut fragments = 0;
+    rasterize_triangle(&vertices, &Rect::screen(16, 16), |fragment| {
+      fragments += 1;
+      let z = 1.0 / fragment.depth;
+      let (x, y) = (fragment.x as f32 + 0.5, fragment.y as f32 + 0.5);
+      // the interpolated point projects on the cell centre
+      assert!((fragment.varying.x - x * z).abs() < 1e-3 * z, "{:?}", fragment);
+      assert!((fragment.varying.y - y * z).abs() < 1e-3 * z, "{:?}", fragment);
+      let [w1, w2, w3] = fragment.barycentric;
+      assert!((w1 + w2 + w3 - 1.0).abs() < 1e-5);
+      assert!((w1 * 1.0 + w2 * 2.0 + w3 * 4.0 - z).abs() < 1e-3 * z);
+    });
+    assert!(fragments > 50);
+  }
+}
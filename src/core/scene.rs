@@ -0,0 +1,246 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::{camera_path::CameraPath, engine::{Camera, LightSource}, material::Material, math::{math::{cross_prod, dot}, matrix::Mat4, vector::Vec3}, mesh::Mesh};
+
+/*
+ * Local transform of a node: scale, then rotation (roll, pitch, yaw), then translation.
+ * Angles are in radians.
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct Transform {
+  pub position: Vec3,
+  pub rotation: Vec3,
+  pub scale: Vec3
+}
+
+impl Default for Transform {
+  fn default () -> Self {
+    Transform {
+      position: Vec3::new(0.0, 0.0, 0.0),
+      rotation: Vec3::new(0.0, 0.0, 0.0),
+      scale: Vec3::new(1.0, 1.0, 1.0)
+    }
+  }
+}
+
+impl Transform {
+  pub fn at (position: Vec3) -> Transform {
+    Transform { position, ..Transform::default() }
+  }
+
+  pub fn matrix (&self) -> Mat4 {
+    Mat4::translation(self.position)
+      * Mat4::rotation_y(self.rotation.y)
+      * Mat4::rotation_x(self.rotation.x)
+      * Mat4::rotation_z(self.rotation.z)
+      * Mat4::scaling(self.scale)
+  }
+
+  /*
+   * Transform giving the matrix, made of a translation, a rotation and a scale without shear.
+   * A mirroring matrix gets a negative scale on x.
+   */
+  pub fn from_matrix (matrix: &Mat4) -> Transform {
+    let m = &matrix.m;
+    let column = |j: usize| Vec3::new(m[0][j], m[1][j], m[2][j]);
+    let (x, y, z) = (column(0), column(1), column(2));
+    let determinant = dot(x, cross_prod(y, z));
+    let scale = Vec3::new(x.length() * determinant.signum(), y.length(), z.length());
+    // rotation left once the scale is divided out, R = Ry(yaw) * Rx(pitch) * Rz(roll)
+    let divisors = [scale.x, scale.y, scale.z].map(|s| if s == 0.0 { 1.0 } else { s });
+    let r = |i: usize, j: usize| m[i][j] / divisors[j];
+    let pitch = (-r(1, 2)).clamp(-1.0, 1.0).asin();
+    let (yaw, roll) = if r(1, 2).abs() < 0.9999 {
+      (f32::atan2(r(0, 2), r(2, 2)), f32::atan2(r(1, 0), r(1, 1)))
+    } else {
+      // gimbal lock, the roll is taken by the yaw
+      (f32::atan2(-r(2, 0), r(0, 0)), 0.0)
+    };
+    Transform { position: Vec3::new(m[0][3], m[1][3], m[2][3]), rotation: Vec3::new(pitch, yaw, roll), scale }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Node {
+  pub name: String,
+  pub transform: Transform,
+  pub parent: Option<usize>,
+  pub mesh: Option<usize>,
+  // replaces every material of the mesh
+  pub material: Option<Material>
+}
+
+impl Node {
+  pub fn new (name: &str, mesh: Option<usize>) -> Node {
+    Node { name: String::from(name), mesh, ..Node::default() }
+  }
+}
+
+// revisions given to the scenes, a scene takes a new one each time its meshes change
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_revision () -> u64 {
+  NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+/*
+ * Meshes are shared by the nodes referencing them, a node comes after its parent.
+ * Without camera nor light, the engine places default ones, the camera path drives the camera when played.
+ */
+#[derive(Debug, Clone)]
+pub struct Scene {
+  meshes: Vec<Mesh>,
+  pub nodes: Vec<Node>,
+  pub lights: Vec<LightSource>,
+  pub camera: Option<Camera>,
+  pub camera_path: Option<CameraPath>,
+  // tells the engine whether what it computed from the meshes still holds
+  revision: u64
+}
+
+impl Default for Scene {
+  fn default () -> Self {
+    Scene { meshes: vec![], nodes: vec![], lights: vec![], camera: None, camera_path: None, revision: next_revision() }
+  }
+}
+
+impl Scene {
+  pub fn new () -> Scene {
+    Scene::default()
+  }
+
+  pub fn meshes (&self) -> &[Mesh] {
+    &self.meshes
+  }
+
+  /*
+   * Mesh to edit in place, the scene taking a new revision
+   */
+  pub fn mesh_mut (&mut self, index: usize) -> &mut Mesh {
+    self.revision = next_revision();
+    &mut self.meshes[index]
+  }
+
+  /*
+   * Number unique to the scene and to the state of its meshes
+   */
+  pub fn revision (&self) -> u64 {
+    self.revision
+  }
+
+  /*
+   * Scene made of a single node displaying the mesh
+   */
+  pub fn from_mesh (name: &str, mesh: Mesh) -> Scene {
+    let mut scene = Scene::new();
+    let mesh = scene.add_mesh(mesh);
+    scene.add_node(Node::new(name, Some(mesh)));
+    scene
+  }
+
+  pub fn add_mesh (&mut self, mesh: Mesh) -> usize {
+    self.revision = next_revision();
+    self.meshes.push(mesh);
+    self.meshes.len() - 1
+  }
+
+  pub fn add_node (&mut self, node: Node) -> usize {
+    if node.parent.is_some_and(|parent| parent >= self.nodes.len()) {
+      panic!("The parent of the node '{}' must be added before it", node.name);
+    }
+    if node.mesh.is_some_and(|mesh| mesh >= self.meshes.len()) {
+      panic!("The node '{}' references an unknown mesh", node.name);
+    }
+    self.nodes.push(node);
+    self.nodes.len() - 1
+  }
+
+  /*
+   * Corners (min, max) of the box containing every drawn vertex in the world, None for an empty scene
+   */
+  pub fn bounds (&self) -> Option<(Vec3, Vec3)> {
+    self.bounds_of(&vec![true; self.nodes.len()])
+  }
+
+  /*
+   * Box containing the node and its descendants
+   */
+  pub fn node_bounds (&self, index: usize) -> Option<(Vec3, Vec3)> {
+    let mut in_subtree = vec![false; self.nodes.len()];
+    for (i, node) in self.nodes.iter().enumerate().skip(index) {
+      in_subtree[i] = i == index || node.parent.is_some_and(|parent| in_subtree[parent]);
+    }
+    self.bounds_of(&in_subtree)
+  }
+
+  fn bounds_of (&self, selected: &[bool]) -> Option<(Vec3, Vec3)> {
+    let world = self.world_transforms();
+    self.nodes.iter().zip(&world).zip(selected)
+      .filter(|(_, &selected)| selected)
+      .filter_map(|((node, transform), _)| node.mesh.map(|mesh| (&self.meshes[mesh], transform)))
+      .flat_map(|(mesh, transform)| mesh.triangles.iter()
+        .flat_map(|triangle| [triangle.v1, triangle.v2, triangle.v3])
+        .chain(mesh.points.iter().map(|point| point.position))
+        .map(move |v| transform.transform_point(v)))
+      .fold(None, |bounds, v| match bounds {
+        Some((min, max)) => Some((
+          Vec3::new(f32::min(min.x, v.x), f32::min(min.y, v.y), f32::min(min.z, v.z)),
+          Vec3::new(f32::max(max.x, v.x), f32::max(max.y, v.y), f32::max(max.z, v.z))
+        )),
+        None => Some((v, v))
+      })
+  }
+
+  /*
+   * Single mesh of every drawn triangle and point placed in the world, the node materials replacing the mesh ones
+   */
+  pub fn baked_mesh (&self) -> Mesh {
+    let mut baked = Mesh::default();
+    for (_, mesh) in self.baked_nodes() {
+      baked.append(mesh);
+    }
+    baked
+  }
+
+  /*
+   * Mesh of each node drawing one, placed in the world, the node material replacing the mesh ones
+   */
+  pub fn baked_nodes (&self) -> Vec<(&Node, Mesh)> {
+    self.nodes.iter().zip(self.world_transforms())
+      .filter_map(|(node, transform)| node.mesh.map(|mesh| (node, &self.meshes[mesh], transform)))
+      .map(|(node, mesh, transform)| {
+        let material = |index: Option<usize>| match &node.material {
+          Some(_) => Some(0),
+          None => index
+        };
+        let baked = Mesh {
+          triangles: mesh.triangles.iter()
+            .map(|triangle| triangle.transform(&transform).with_material(material(triangle.material)))
+            .collect(),
+          points: mesh.points.iter().map(|point| point.transform(&transform)).collect(),
+          materials: match &node.material {
+            Some(material) => vec![material.clone()],
+            None => mesh.materials.clone()
+          }
+        };
+        (node, baked)
+      })
+      .collect()
+  }
+
+  /*
+   * Transform from each node to the world, computed from the local transforms
+   */
+  pub fn world_transforms (&self) -> Vec<Mat4> {
+    let mut world: Vec<Mat4> = Vec::with_capacity(self.nodes.len());
+    for node in &self.nodes {
+      let local = node.transform.matrix();
+      let transform = match node.parent {
+        Some(parent) => world[parent] * local,
+        None => local
+      };
+      world.push(transform);
+    }
+    world
+  }
+}
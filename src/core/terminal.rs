@@ -0,0 +1,65 @@
+use std::io::{self, stdout};
+
+use crossterm::{
+  cursor::{Hide, Show},
+  event::{DisableMouseCapture, EnableMouseCapture, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+  execute,
+  terminal::{disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, Clear, ClearType}
+};
+
+/*
+ * Terminal set up for the interactive loop: keys read without waiting for enter,
+ * hidden cursor, optional mouse capture and key releases reported where the terminal
+ * supports the kitty keyboard protocol. Everything is restored when dropped,
+ * also when the loop ends with an error or a panic.
+ */
+pub struct TerminalSession {
+  mouse_capture: bool,
+  keyboard_enhancement: bool
+}
+
+impl TerminalSession {
+  pub fn start (mouse_capture: bool) -> io::Result<TerminalSession> {
+    enable_raw_mode()?;
+    // from here, the drop restores what has been changed
+    let mut session = TerminalSession { mouse_capture: false, keyboard_enhancement: false };
+    execute!(stdout(), Hide)?;
+    if supports_keyboard_enhancement().unwrap_or(false) {
+      let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+      execute!(stdout(), PushKeyboardEnhancementFlags(flags))?;
+      session.keyboard_enhancement = true;
+    }
+    if mouse_capture {
+      execute!(stdout(), EnableMouseCapture)?;
+      session.mouse_capture = true;
+    }
+    Ok(session)
+  }
+
+  /*
+   * Erase the whole terminal, the previous frame stays visible around a smaller one
+   */
+  pub fn clear (&mut self) -> io::Result<()> {
+    execute!(stdout(), Clear(ClearType::All))
+  }
+
+  /*
+   * Whether the key releases are reported, the Windows console always does
+   */
+  pub fn release_events (&self) -> bool {
+    self.keyboard_enhancement || cfg!(windows)
+  }
+}
+
+impl Drop for TerminalSession {
+  fn drop (&mut self) {
+    if self.keyboard_enhancement {
+      let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+    }
+    if self.mouse_capture {
+      let _ = execute!(stdout(), DisableMouseCapture);
+    }
+    let _ = execute!(stdout(), Show);
+    let _ = disable_raw_mode();
+  }
+}
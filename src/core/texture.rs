@@ -0,0 +1,89 @@
+use super::math::vector::{Vec2, Vec3};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextureFilter {
+  Nearest,
+  Bilinear
+}
+
+impl std::str::FromStr for TextureFilter {
+  type Err = String;
+  fn from_str (value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "nearest" => Ok(TextureFilter::Nearest),
+      "bilinear" => Ok(TextureFilter::Bilinear),
+      _ => Err(format!("Unknown texture filter '{}' (nearest, bilinear)", value))
+    }
+  }
+}
+
+impl std::fmt::Display for TextureFilter {
+  fn fmt (&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      TextureFilter::Nearest => write!(f, "nearest"),
+      TextureFilter::Bilinear => write!(f, "bilinear")
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextureWrap {
+  Repeat,
+  Clamp
+}
+
+/*
+ * RGB image, each channel between 0.0 and 1.0, stored row by row from the top
+ */
+#[derive(Debug, Clone)]
+pub struct Texture {
+  pub width: usize,
+  pub height: usize,
+  pub pixels: Vec<Vec3>,
+  pub wrap: TextureWrap
+}
+
+impl Texture {
+  pub fn new (width: usize, height: usize, pixels: Vec<Vec3>) -> Texture {
+    if width < 1 || height < 1 || pixels.len() != width * height {
+      panic!("The texture size doesn't match its pixels");
+    }
+    Texture { width, height, pixels, wrap: TextureWrap::Repeat }
+  }
+
+  pub fn texel (&self, x: isize, y: isize) -> Vec3 {
+    let (width, height) = (self.width as isize, self.height as isize);
+    let (x, y) = match self.wrap {
+      TextureWrap::Repeat => (x.rem_euclid(width), y.rem_euclid(height)),
+      TextureWrap::Clamp => (x.clamp(0, width - 1), y.clamp(0, height - 1))
+    };
+    self.pixels[(y * width + x) as usize]
+  }
+
+  /*
+   * The v axis goes up (OBJ convention) while the rows go down
+   */
+  pub fn sample (&self, uv: Vec2, filter: TextureFilter) -> Vec3 {
+    let x = uv.x * self.width as f32;
+    let y = (1.0 - uv.y) * self.height as f32;
+    match filter {
+      TextureFilter::Nearest => self.texel(x.floor() as isize, y.floor() as isize),
+      TextureFilter::Bilinear => {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let top = (1.0 - fx) * self.texel(x0, y0) + fx * self.texel(x0 + 1, y0);
+        let bottom = (1.0 - fx) * self.texel(x0, y0 + 1) + fx * self.texel(x0 + 1, y0 + 1);
+        (1.0 - fy) * top + fy * bottom
+      }
+    }
+  }
+}
+
+/*
+ * Perceived brightness of a colour (Rec. 709)
+ */
+pub fn luminance (color: Vec3) -> f32 {
+  0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
@@ -0,0 +1,78 @@
+use super::{math::vector::Vec3, raster::{bounding_box, rasterize_triangle, Fragment, RasterVertex, Rect, Varying}};
+
+pub const TILE_WIDTH: usize = 32;
+pub const TILE_HEIGHT: usize = 8;
+
+/*
+ * Mutable view on consecutive rows of the frame buffers, starting at the row `y0`
+ */
+pub struct FrameBand<'a> {
+  pub y0: usize,
+  pub width: usize,
+  pub pixels: &'a mut [char],
+  pub depths: &'a mut [f32],
+  pub colors: &'a mut [Vec3]
+}
+
+impl FrameBand<'_> {
+  /*
+   * Rasterize a triangle inside `clip` with a depth test, `shader` gives the char and colour of each visible fragment
+   */
+  pub fn put<V: Varying, S: Fn(&Fragment<V>) -> Option<(char, Vec3)>> (&mut self, vertices: &[RasterVertex<V>; 3], clip: &Rect, shader: S) {
+    let (y0, width) = (self.y0, self.width);
+    rasterize_triangle(vertices, clip, |fragment| {
+      let index = (fragment.y - y0) * width + fragment.x;
+      if fragment.depth <= self.depths[index] {
+        return;
+      }
+      if let Some((char, color)) = shader(fragment) {
+        self.depths[index] = fragment.depth;
+        self.pixels[index] = char;
+        self.colors[index] = color;
+      }
+    });
+  }
+}
+
+/*
+ * Screen area with the triangles overlapping it, in submission order
+ */
+#[derive(Debug)]
+pub struct Tile {
+  pub bounds: Rect,
+  pub triangles: Vec<usize>
+}
+
+/*
+ * Split the screen in tiles and list the triangles touching each one.
+ * Tiles are grouped by rows of TILE_HEIGHT cells.
+ */
+pub fn bin_triangles<'a, V: Varying + 'a> (triangles: impl Iterator<Item = &'a [RasterVertex<V>; 3]>, width: usize, height: usize) -> Vec<Vec<Tile>> {
+  let columns = width.div_ceil(TILE_WIDTH);
+  let rows = height.div_ceil(TILE_HEIGHT);
+  let mut tiles: Vec<Vec<Tile>> = (0..rows).map(|row| {
+    (0..columns).map(|column| Tile {
+      bounds: Rect::new(
+        column * TILE_WIDTH,
+        row * TILE_HEIGHT,
+        ((column + 1) * TILE_WIDTH).min(width),
+        ((row + 1) * TILE_HEIGHT).min(height)
+      ),
+      triangles: vec![]
+    }).collect()
+  }).collect();
+
+  let screen = Rect::screen(width, height);
+  for (index, vertices) in triangles.enumerate() {
+    let bounds = bounding_box(vertices, &screen);
+    if bounds.is_empty() {
+      continue;
+    }
+    for row in &mut tiles[bounds.y0 / TILE_HEIGHT..bounds.y1.div_ceil(TILE_HEIGHT)] {
+      for tile in &mut row[bounds.x0 / TILE_WIDTH..bounds.x1.div_ceil(TILE_WIDTH)] {
+        tile.triangles.push(index);
+      }
+    }
+  }
+  tiles
+}
@@ -0,0 +1,119 @@
+use std::{thread, time::{Duration, Instant}};
+
+// longest time simulated for one frame, a stalled terminal does not make the camera jump
+const MAX_FRAME_TIME: f32 = 0.25;
+// a frame runs at most MAX_FRAME_TIME * MAX_TICK_RATE simulation steps
+pub const MAX_TICK_RATE: f32 = 1000.0;
+// lowest frame rate limit, one frame every 10 seconds
+pub const MIN_FPS: f32 = 0.1;
+
+/*
+ * Pace of the interactive loop: the simulation advances by steps of `1 / tick_rate` seconds,
+ * or by the duration of the last frame with `variable_step`, and at most `target_fps`
+ * frames are drawn per second (no limit with 0)
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct TimingSettings {
+  pub target_fps: f32,
+  pub tick_rate: f32,
+  pub variable_step: bool
+}
+
+impl Default for TimingSettings {
+  fn default () -> Self {
+    TimingSettings { target_fps: 30.0, tick_rate: 60.0, variable_step: false }
+  }
+}
+
+impl TimingSettings {
+  /*
+   * Error when the frame rate is neither 0 nor at least MIN_FPS, or the tick rate is not in ]0, MAX_TICK_RATE]
+   */
+  pub fn check (&self) -> Result<(), String> {
+    if !(self.target_fps == 0.0 || (MIN_FPS..=f32::MAX).contains(&self.target_fps)) {
+      return Err(format!("The frame rate must be 0 (no limit) or at least {}, not {}", MIN_FPS, self.target_fps));
+    }
+    if !(self.tick_rate > 0.0 && self.tick_rate <= MAX_TICK_RATE) {
+      return Err(format!("The tick rate must be above 0 and at most {}, not {}", MAX_TICK_RATE, self.tick_rate));
+    }
+    Ok(())
+  }
+}
+
+/*
+ * Clock of the interactive loop, splitting the time elapsed between the frames in simulation steps
+ */
+#[derive(Debug)]
+pub struct FrameClock {
+  pub settings: TimingSettings,
+  last: Instant,
+  // seconds not simulated yet, less than a step
+  accumulator: f32
+}
+
+impl FrameClock {
+  pub fn new (settings: TimingSettings, now: Instant) -> FrameClock {
+    FrameClock { settings, last: now, accumulator: 0.0 }
+  }
+
+  /*
+   * Seconds elapsed since the previous frame
+   */
+  pub fn tick (&mut self, now: Instant) -> f32 {
+    let elapsed = now.duration_since(self.last).as_secs_f32().min(MAX_FRAME_TIME);
+    self.last = now;
+    elapsed
+  }
+
+  /*
+   * Number and duration of the simulation steps covering `elapsed` seconds
+   */
+  pub fn steps (&mut self, elapsed: f32) -> (usize, f32) {
+    if self.settings.variable_step || self.settings.tick_rate <= 0.0 {
+      return (1, elapsed);
+    }
+    let step = 1.0 / self.settings.tick_rate.min(MAX_TICK_RATE);
+    self.accumulator += elapsed;
+    let count = (self.accumulator / step).floor();
+    self.accumulator -= count * step;
+    (count as usize, step)
+  }
+
+  /*
+   * Sleep until the next frame is due
+   */
+  pub fn wait (&self, frame_start: Instant) {
+    if self.settings.target_fps > 0.0 {
+      let frame = Duration::from_secs_f32(1.0 / self.settings.target_fps.max(MIN_FPS));
+      if let Some(rest) = frame.checked_sub(frame_start.elapsed()) {
+        thread::sleep(rest);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rates_are_checked () {
+    let timing = |target_fps: f32, tick_rate: f32| TimingSettings { target_fps, tick_rate, variable_step: false };
+    assert!(timing(0.0, 60.0).check().is_ok());
+    assert!(timing(30.0, MAX_TICK_RATE).check().is_ok());
+    for fps in [-1.0, 1e-40, f32::NAN, f32::INFINITY] {
+      assert!(timing(fps, 60.0).check().is_err(), "{}", fps);
+    }
+    for tick_rate in [0.0, -60.0, MAX_TICK_RATE * 2.0, f32::NAN, f32::INFINITY] {
+      assert!(timing(30.0, tick_rate).check().is_err(), "{}", tick_rate);
+    }
+  }
+
+  #[test]
+  fn steps_of_a_frame_are_bounded () {
+    let mut clock = FrameClock::new(TimingSettings { tick_rate: 1e30, ..TimingSettings::default() }, Instant::now());
+    let (steps, step) = clock.steps(MAX_FRAME_TIME);
+    assert_eq!(step, 1.0 / MAX_TICK_RATE);
+    assert!(steps <= (MAX_FRAME_TIME * MAX_TICK_RATE) as usize + 1);
+  }
+}
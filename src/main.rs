@@ -1,26 +1,85 @@
 pub mod core;
 pub mod tools;
 
-use std::path::Path;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 use std::{env, io};
 
 use crossterm::event::{read, Event, KeyCode};
-use getopts::Options;
+use getopts::{Matches, Options};
 use tools::logger::Logger;
 
-use crate::core::engine::Engine;
+use crate::core::bindings::{KeyBindings, Layout};
+use crate::core::camera_path::CameraPath;
+use crate::core::engine::{ColorMode, Engine, RenderMode};
+use crate::core::hud::{HudCorner, HudItem};
+use crate::core::profiler::Profiler;
 
-use crate::core::math::triangle::Triangle3D;
+use crate::core::projection::Projection;
+use crate::core::scene::Scene;
+use crate::core::texture::TextureFilter;
+use crate::tools::config_file::{self, ConfigFile, LogSettings};
+use crate::tools::logger::{DEFAULT_KEEP, DEFAULT_LOG_FILE, DEFAULT_MAX_SIZE};
 use crate::tools::configuration::Configuration;
-use crate::tools::wavefront;
+use crate::tools::camera_path_file::read_camera_path_file;
+use crate::tools::key_bindings_file::read_key_bindings_file;
+use crate::tools::scene_file::{self, SceneFile};
+use crate::tools::benchmark;
+use crate::tools::mesh_file::{read_mesh_scene, write_scene_file};
+use crate::tools::stl::StlEncoding;
 
 fn main() {
   // Parse parameters
   let args: Vec<String> = env::args().collect();
   let program = args[0].clone();
   let mut opts = Options::new();
-  opts.optflag("d", "debug", "enable debugger (log every message in the file engine_3D.log)");
-  opts.optopt("o", "object_path", "set path to 3d object (wavefront format)", "obj/cube.obj");
+  opts.optopt("", "config", "set path to a config file (TOML), by default engine_3d.toml of the current directory or of ~/.config/engine_3d", "engine_3d.toml");
+  opts.optflag("", "print_config", "print the effective settings as a config file and exit");
+  opts.optflag("d", "debug", "enable debugger (log the debug messages in the log file)");
+  opts.optopt("", "log_level", "set the log level (off, error, warn, info, debug, trace), also per module", "warn,tools::wavefront=trace");
+  opts.optopt("", "log_file", "set path to the log file, created when needed", "engine_3D.log");
+  opts.optopt("", "log_max_size", "set the size in bytes of the log file before it is rotated, 0 never", "10485760");
+  opts.optopt("", "log_keep", "set the number of rotated log files kept", "3");
+  opts.optflagopt("", "log_enabled", "write the log file (true, false writes nothing)", "true");
+  opts.optopt("o", "object_path", "set path to 3d object (wavefront .obj, STL .stl, PLY .ply or glTF .gltf/.glb)", "obj/cube.obj");
+  opts.optopt("s", "scene", "set path to a scene file (TOML) describing several objects, replaces the object", "obj/world.toml");
+  opts.optopt("c", "color_mode", "set the colour output (monochrome, truecolor)", "monochrome");
+  opts.optopt("f", "texture_filter", "set the texture filtering (nearest, bilinear)", "bilinear");
+  opts.optopt("m", "render_mode", "set the render mode (filled, wireframe, points, hidden_line), toggled with the key 'm'", "filled");
+  opts.optopt("p", "projection", "set the camera projection (perspective[:fov in degrees], orthographic[:view height]), zoomed with the keys '+' and '-'", "perspective:90");
+  opts.optopt("k", "camera_path", "play the camera keyframes of a TOML file (also in benchmark), toggled with the key 'k'", "obj/flythrough.toml");
+  opts.optopt("", "width", "set the width of the view in cells instead of the terminal one", "120");
+  opts.optopt("", "height", "set the height of the view in cells instead of the terminal one", "40");
+  opts.optopt("", "fov", "set the vertical field of view in degrees of a perspective, an error with an orthographic projection", "90");
+  opts.optopt("", "near", "set the distance from the camera to the near clipping plane", "0.1");
+  opts.optopt("", "far", "set the distance from the camera to the far clipping plane (default: none)", "100");
+  opts.optopt("", "shading_ramp", "set the chars shading the surfaces, from the darkest to the brightest", ".,;la#@");
+  opts.optopt("", "face_culling", "skip the triangles facing away from the camera (true, false)", "false");
+  opts.optopt("j", "threads", "set the number of rasterization threads (default: number of cores)", "4");
+  opts.optopt("", "layout", "set the default keys for a keyboard layout (qwerty, azerty)", "azerty");
+  opts.optopt("", "keys", "set path to a key bindings file (TOML) mapping actions to keys", "obj/keys.toml");
+  opts.optflagopt("", "mouse", "turn (left drag), pan (middle drag) and zoom (wheel) with the mouse (true, false leaves it to the terminal)", "true");
+  opts.optopt("", "mouse_sensitivity", "set the angle in radians turned by a drag of one cell", "0.02");
+  opts.optflagopt("", "invert_y", "invert the vertical axis of the mouse (true, false)", "true");
+  opts.optopt("", "move_speed", "set the moving speed in units per second, multiplied with ctrl+wheel", "5");
+  opts.optopt("", "acceleration", "set the acceleration in units per second squared to reach the moving speed", "20");
+  opts.optopt("", "turn_speed", "set the turning speed with the arrow keys in radians per second", "1.5");
+  opts.optopt("", "key_timeout", "set the milliseconds without repeat after which a held key is released, when the terminal sends no release", "500");
+  opts.optopt("", "fps", "set the target frames per second, 0 draws as fast as possible", "30");
+  opts.optopt("", "tick_rate", "set the simulation steps per second", "60");
+  opts.optflagopt("", "variable_step", "advance the simulation by the duration of each frame instead of fixed steps (true, false)", "true");
+  opts.optflagopt("", "hud", "show the overlay with the frame rate, the triangle counts and the camera (true, false), toggled with the key 'h'", "true");
+  opts.optopt("", "hud_items", "set the lines of the overlay, in order (fps, times, triangles, camera)", "fps,camera");
+  opts.optopt("", "hud_corner", "set the corner of the overlay (top_left, top_right, bottom_left, bottom_right)", "top_left");
+  opts.optflag("", "profile", "time the stages of each frame and print their min, average and 99th percentile on exit");
+  opts.optopt("", "trace", "write the stage timers to a Chrome trace-event file on exit (chrome://tracing, Perfetto)", "trace.json");
+  opts.optopt("", "export", "write the object, or the scene placed in the world, to a mesh file (.obj with its .mtl, .stl) and exit", "out.obj");
+  opts.optflag("", "export_local", "export each mesh once in its own coordinates instead of the nodes placed in the world (.obj)");
+  opts.optflag("", "export_ascii", "export an ASCII STL file instead of a binary one (.stl)");
+  opts.optflagopt("b", "benchmark", "render the sample assets (or the object) without display and print the triangles per second", "100");
   opts.optflag("h", "help", "print this help menu");
   let matches = match opts.parse(&args[1..]) {
     Ok(m) => { m }
@@ -32,10 +91,30 @@ fn main() {
     return;
   }
   
-  let mut logger: Logger = Logger::new();
-  if matches.opt_present("d") {
-    logger.enable_log();
+  // the settings of the config file come first, then the scene file and the options override them
+  let config_path: Option<PathBuf> = matches.opt_str("config").map(PathBuf::from).or_else(config_file::find_config_file);
+  let config: ConfigFile = match &config_path {
+    Some(path) => config_file::read_config_file(path).unwrap_or_else(|e| panic!("Error to read the config file: {}", e)),
+    None => ConfigFile::default()
+  };
+
+  let log_settings = LogSettings {
+    path: Some(matches.opt_str("log_file").or_else(|| config.log.path.clone()).unwrap_or_else(|| DEFAULT_LOG_FILE.to_string())),
+    max_size: Some(parse_option(&matches, "log_max_size").or(config.log.max_size).unwrap_or(DEFAULT_MAX_SIZE)),
+    keep: Some(parse_option(&matches, "log_keep").or(config.log.keep).unwrap_or(DEFAULT_KEEP)),
+    ..LogSettings::default()
+  };
+  let mut logger: Logger = Logger::to_file(Path::new(log_settings.path.as_deref().unwrap_or(DEFAULT_LOG_FILE)), log_settings.max_size.unwrap_or_default(), log_settings.keep.unwrap_or_default());
+  match parse_flag(&matches, "log_enabled").or(config.log.enabled) {
+    Some(true) => logger.enable_log(),
+    Some(false) => logger.disable_log(),
+    None => {}
+  }
+  for spec in [config.log.level.clone(), matches.opt_present("d").then(|| "debug".to_string()), matches.opt_str("log_level")].into_iter().flatten() {
+    logger.set_spec(&spec).unwrap_or_else(|e| panic!("{}", e));
   }
+  #[cfg(feature = "log")]
+  let _ = tools::logger::install_log_facade(&logger);
   let object_path_str: String = match matches.opt_str("o") {
     Some(path_str) => path_str,
     None => "obj/cube.obj".to_string(),
@@ -44,16 +123,221 @@ fn main() {
   
   // initialize 3d engine
   let configuration:Configuration = Configuration::new();
-  let mut engine: Engine = Engine::new(configuration.width, configuration.height - 1, &logger);
-  print_welcome (&logger, &configuration, &engine, &args);
+  // the last line is left to the cursor, a terminal too small still gets one cell
+  let (width, height) = config.view_size((configuration.width, configuration.height.saturating_sub(1)));
+  let width: usize = parse_option(&matches, "width").unwrap_or(width);
+  let height: usize = parse_option(&matches, "height").unwrap_or(height);
+  let mut engine: Engine = Engine::new(width.max(1), height.max(1), &logger);
+  config.apply(&mut engine).unwrap_or_else(|e| panic!("{}", e));
+  if matches.opt_present("width") || matches.opt_present("height") {
+    engine.follow_terminal = false;
+  }
+
+  // the settings of the scene file come first, the options override them
+  let scene_file: Option<SceneFile> = matches.opt_str("s").map(|scene_path| {
+    scene_file::read_scene_file(Path::new(&scene_path), &logger).expect("Error to read the scene file")
+  });
+  if let Some(scene_file) = &scene_file {
+    scene_file.apply(&mut engine).unwrap_or_else(|e| panic!("{}", e));
+  }
+  apply_options(&matches, &mut engine);
+  if matches.opt_present("print_config") {
+    let log = LogSettings { enabled: Some(logger.is_enabled()), level: Some(logger.spec()), ..log_settings };
+    let effective = ConfigFile::effective(&engine, log);
+    print!("{}", effective.to_toml().unwrap_or_else(|e| panic!("Error to print the config: {}", e)));
+    return;
+  }
+  if let Some(export_path) = matches.opt_str("export") {
+    let scene: Scene = match scene_file {
+      Some(scene_file) => scene_file.scene,
+      None => read_mesh_scene(object_path, &logger).unwrap_or_else(|e| panic!("Error to read file: {}", e))
+    };
+    let encoding = if matches.opt_present("export_ascii") { StlEncoding::Ascii } else { StlEncoding::Binary };
+    let triangles = write_scene_file(Path::new(&export_path), &scene, !matches.opt_present("export_local"), encoding)
+      .unwrap_or_else(|e| panic!("Error to export the scene: {}", e));
+    println!("{} triangles written to {}", triangles, export_path);
+    return;
+  }
+  print_welcome (&logger, &configuration, config_path.as_deref(), &engine, &args);
+  let camera_path: Option<CameraPath> = matches.opt_str("k").map(|path| {
+    read_camera_path_file(Path::new(&path)).expect("Error to read the camera path")
+  });
+
+  if matches.opt_present("b") {
+    let frames: usize = match matches.opt_str("b") {
+      Some(frames) => frames.parse().unwrap_or_else(|e| panic!("Invalid number of frames '{}': {}", frames, e)),
+      None => 100,
+    };
+    match scene_file {
+      Some(mut scene_file) => {
+        apply_scene_options(&matches, &mut scene_file.scene, camera_path);
+        let name = matches.opt_str("s").unwrap_or_default();
+        benchmark::run_scene_file(&mut engine, &name, &scene_file.scene, frames, &logger);
+      },
+      None => {
+        let paths: Vec<&Path> = match matches.opt_str("o") {
+          Some(_) => vec![object_path],
+          None => benchmark::SAMPLE_ASSETS.iter().map(Path::new).collect(),
+        };
+        let camera_path = camera_path.unwrap_or_else(benchmark::default_camera_path);
+        benchmark::run(&mut engine, &paths, frames, &camera_path, &logger).expect("Error to run the benchmark");
+      }
+    }
+    finish_profiler(&engine);
+    return;
+  }
 
-  // load object
-  let object: Vec<Triangle3D> = wavefront::read_object_file(object_path, &logger)
-    .expect("Error to read file");
+  let mut scene: Scene = match scene_file {
+    Some(scene_file) => scene_file.scene,
+    None => {
+      // load object
+      let scene: Scene = read_mesh_scene(object_path, &logger)
+        .unwrap_or_else(|e| panic!("Error to read file: {}", e));
+      log_trace!(logger, "Meshes: {:?}", scene.meshes());
+      scene
+    }
+  };
+
+  apply_scene_options(&matches, &mut scene, camera_path);
 
-  logger.log(format!("Triangle: {:?}", object));
   let _ = wait_key();
-  let _ = engine.play_loop(object);
+  let _ = engine.play_loop(scene);
+  // the terminal is restored, the summary can be printed
+  finish_profiler(&engine);
+}
+
+/*
+ * Print the profile and write the trace, exit with an error when the trace can't be written
+ */
+fn finish_profiler (engine: &Engine) {
+  if let Err(e) = engine.profiler.finish() {
+    eprintln!("Error to write the trace: {}", e);
+    log_error!(engine.logger, "Error to write the trace: {}", e);
+    std::process::exit(1);
+  }
+}
+
+fn apply_options (matches: &Matches, engine: &mut Engine) {
+  if let Some(color_mode) = matches.opt_str("c") {
+    engine.color_mode = color_mode.parse::<ColorMode>().unwrap_or_else(|e| panic!("{}", e));
+  }
+  if let Some(texture_filter) = matches.opt_str("f") {
+    engine.texture_filter = texture_filter.parse::<TextureFilter>().unwrap_or_else(|e| panic!("{}", e));
+  }
+  if let Some(render_mode) = matches.opt_str("m") {
+    engine.render_mode = render_mode.parse::<RenderMode>().unwrap_or_else(|e| panic!("{}", e));
+  }
+  let layout: Option<Layout> = matches.opt_str("layout").map(|layout| layout.parse::<Layout>().unwrap_or_else(|e| panic!("{}", e)));
+  match matches.opt_str("keys") {
+    Some(path) => {
+      engine.controls.bindings = read_key_bindings_file(Path::new(&path), layout.unwrap_or_default()).expect("Error to read the key bindings");
+    },
+    None => if let Some(layout) = layout {
+      engine.controls.bindings = KeyBindings::profile(layout);
+    }
+  }
+  if let Some(mouse) = parse_flag(matches, "mouse") {
+    engine.controls.mouse.enabled = mouse;
+  }
+  if let Some(sensitivity) = matches.opt_str("mouse_sensitivity") {
+    engine.controls.mouse.sensitivity = sensitivity.parse::<f32>().unwrap_or_else(|e| panic!("Invalid mouse sensitivity '{}': {}", sensitivity, e));
+  }
+  if let Some(invert_y) = parse_flag(matches, "invert_y") {
+    engine.controls.mouse.invert_y = invert_y;
+  }
+  let movement = &mut engine.controls.movement;
+  for (name, value) in [("move_speed", &mut movement.speed), ("acceleration", &mut movement.acceleration), ("turn_speed", &mut movement.turn_speed)] {
+    if let Some(number) = matches.opt_str(name) {
+      *value = number.parse::<f32>().unwrap_or_else(|e| panic!("Invalid {} '{}': {}", name, number, e));
+    }
+  }
+  if let Some(key_timeout) = parse_option(matches, "key_timeout") {
+    engine.controls.movement.key_timeout = Duration::from_millis(key_timeout);
+  }
+  let timing = &mut engine.timing;
+  for (name, value) in [("fps", &mut timing.target_fps), ("tick_rate", &mut timing.tick_rate)] {
+    if let Some(number) = matches.opt_str(name) {
+      *value = number.parse::<f32>().unwrap_or_else(|e| panic!("Invalid {} '{}': {}", name, number, e));
+    }
+  }
+  if let Some(variable_step) = parse_flag(matches, "variable_step") {
+    engine.timing.variable_step = variable_step;
+  }
+  engine.timing.check().unwrap_or_else(|e| panic!("{}", e));
+  if let Some(hud) = parse_flag(matches, "hud") {
+    engine.hud.visible = hud;
+  }
+  if let Some(items) = matches.opt_str("hud_items") {
+    engine.hud.items = items.split(',')
+      .map(|item| item.trim().parse::<HudItem>().unwrap_or_else(|e| panic!("{}", e)))
+      .collect();
+  }
+  if let Some(corner) = matches.opt_str("hud_corner") {
+    engine.hud.corner = corner.parse::<HudCorner>().unwrap_or_else(|e| panic!("{}", e));
+  }
+  if let Some(threads) = matches.opt_str("j") {
+    engine.threads = threads.parse::<usize>().unwrap_or_else(|e| panic!("Invalid number of threads '{}': {}", threads, e)).max(1);
+  }
+  if let Some(projection) = matches.opt_str("p") {
+    engine.projection = projection.parse::<Projection>().unwrap_or_else(|e| panic!("{}", e));
+  }
+  if let Some(fov) = parse_option(matches, "fov") {
+    engine.projection = engine.projection.with_fov(fov).unwrap_or_else(|e| panic!("{}", e));
+  }
+  if let Some(near) = parse_option(matches, "near") {
+    engine.z_near = near;
+  }
+  if let Some(far) = parse_option(matches, "far") {
+    engine.z_far = far;
+  }
+  if engine.z_near <= 0.0 || engine.z_far <= engine.z_near {
+    panic!("The clipping distances must verify 0 < near < far, not near={} far={}", engine.z_near, engine.z_far);
+  }
+  if let Some(shading_ramp) = matches.opt_str("shading_ramp").filter(|ramp| !ramp.is_empty()) {
+    engine.shading_ramp = shading_ramp.chars().collect();
+  }
+  if let Some(face_culling) = parse_option(matches, "face_culling") {
+    engine.face_culling = face_culling;
+  }
+  if matches.opt_present("profile") || matches.opt_present("trace") {
+    let trace_path = matches.opt_str("trace").map(PathBuf::from).or_else(|| engine.profiler.trace_path.clone());
+    engine.profiler = Arc::new(Profiler::new(true, trace_path));
+  }
+}
+
+/*
+ * Value of the option parsed, None when the option is not given
+ */
+fn parse_option<T> (matches: &Matches, name: &str) -> Option<T> where T: FromStr, T::Err: Display {
+  matches.opt_str(name).map(|value| value.parse::<T>().unwrap_or_else(|e| panic!("Invalid {} '{}': {}", name, value, e)))
+}
+
+/*
+ * Value of a switch, true when it is given without one: `--hud`, `--hud=false`
+ */
+fn parse_flag (matches: &Matches, name: &str) -> Option<bool> {
+  matches.opt_present(name).then(|| parse_option(matches, name).unwrap_or(true))
+}
+
+/*
+ * Projection options applied to the camera of the scene, a scene without one gets the camera
+ * framed by the engine with `engine.projection`
+ */
+fn apply_scene_options (matches: &Matches, scene: &mut Scene, camera_path: Option<CameraPath>) {
+  if let Some(projection) = matches.opt_str("p") {
+    let projection = projection.parse::<Projection>().unwrap_or_else(|e| panic!("{}", e));
+    if let Some(camera) = scene.camera.as_mut() {
+      camera.projection = projection;
+    }
+  }
+  if let Some(fov) = parse_option(matches, "fov") {
+    if let Some(camera) = scene.camera.as_mut() {
+      camera.projection = camera.projection.with_fov(fov).unwrap_or_else(|e| panic!("{}", e));
+    }
+  }
+  if camera_path.is_some() {
+    scene.camera_path = camera_path;
+  }
 }
 
 fn print_usage(program: &str, opts: Options) {
@@ -61,19 +345,25 @@ fn print_usage(program: &str, opts: Options) {
   print!("{}", opts.usage(&brief));
 }
 
-fn print_welcome (logger: &Logger, configuration: &Configuration, engine: &Engine, args: &Vec<String>) {
+fn print_welcome (logger: &Logger, configuration: &Configuration, config_path: Option<&Path>, engine: &Engine, args: &Vec<String>) {
   let msg = String::from("Welcome on Rust 3D engine !");
   println!("{}", msg);
-  logger.log(msg);
+  log_info!(logger, "{}", msg);
   let msg = format!("Configuration: {:?}", configuration);
   println!("{}", msg);
-  logger.log(msg);
+  log_info!(logger, "{}", msg);
+  let msg = format!("Config file: {:?}", config_path);
+  println!("{}", msg);
+  log_info!(logger, "{}", msg);
   let msg = format!("Engine: {:?}", engine.to_string());
   println!("{}", msg);
-  logger.log(msg);
+  log_info!(logger, "{}", msg);
+  let msg = format!("Keys: {}", engine.controls.bindings);
+  println!("{}", msg);
+  log_info!(logger, "{}", msg);
   let msg = format!("args: {:?}", args);
   println!("{}", msg);
-  logger.log(msg);
+  log_info!(logger, "{}", msg);
 }
 
 fn wait_key () -> io::Result<()> {
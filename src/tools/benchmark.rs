@@ -0,0 +1,77 @@
+use std::{io::Result, path::Path, time::{Duration, Instant}};
+
+use crate::core::{camera_path::CameraPath, engine::{Camera, Engine, LightSource}, math::vector::Vec3, scene::Scene};
+
+use super::{logger::Logger, mesh_file::read_mesh_scene};
+use crate::log_info;
+
+pub const SAMPLE_ASSETS: [&str; 4] = ["obj/cube.obj", "obj/test.obj", "obj/Home.obj", "obj/landscape.obj"];
+
+/*
+ * Camera turning around the origin, used when no path is given
+ */
+pub fn default_camera_path() -> CameraPath {
+    CameraPath::circle(Vec3::new(0.0, 0.0, 0.0), 2.0, 0.5, 10.0)
+}
+
+fn print_header() {
+    println!("{:<24} {:>10} {:>8} {:>12} {:>14}", "object", "triangles", "frames", "time (ms)", "triangles/s");
+}
+
+/*
+ * Render each object `frames` times with the camera following `camera_path`,
+ * without touching the terminal, and print the throughput
+ */
+pub fn run(engine: &mut Engine, paths: &[&Path], frames: usize, camera_path: &CameraPath, logger: &Logger) -> Result<()> {
+    print_header();
+    for path in paths {
+        let mut scene = read_mesh_scene(path, logger)?;
+        if scene.lights.is_empty() {
+            scene.lights.push(LightSource::at(&Vec3::new(5.0, 5.0, 5.0)));
+        }
+        run_scene(engine, &path.display().to_string(), &scene, frames, camera_path, logger);
+    }
+    Ok(())
+}
+
+/*
+ * Render the scene `frames` times spread over the camera path and print one row
+ */
+pub fn run_scene(engine: &mut Engine, name: &str, scene: &Scene, frames: usize, camera_path: &CameraPath, logger: &Logger) {
+    let mut cam = scene.camera.clone().unwrap_or_else(|| Camera {
+        projection: engine.projection,
+        ..Camera::default()
+    });
+    let mut elapsed = Duration::ZERO;
+    for frame in 0..frames {
+        camera_path.apply(&mut cam, frame as f32 / frames as f32 * camera_path.duration());
+        engine.clear(' ');
+        let start = Instant::now();
+        engine.put_scene(scene, &cam);
+        elapsed += start.elapsed();
+        engine.profiler.end_frame();
+    }
+    let triangles_per_frame: usize = scene.nodes.iter()
+        .filter_map(|node| node.mesh)
+        .map(|mesh| scene.meshes()[mesh].triangles.len())
+        .sum();
+    let msg = format!(
+        "{:<24} {:>10} {:>8} {:>12.1} {:>14.0}",
+        name,
+        triangles_per_frame,
+        frames,
+        elapsed.as_secs_f64() * 1000.0,
+        (triangles_per_frame * frames) as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    println!("{}", msg);
+    log_info!(logger, "{}", msg);
+}
+
+/*
+ * Benchmark of a whole scene, its camera path (or the default one) drives the camera
+ */
+pub fn run_scene_file(engine: &mut Engine, name: &str, scene: &Scene, frames: usize, logger: &Logger) {
+    print_header();
+    let camera_path = scene.camera_path.clone().unwrap_or_else(default_camera_path);
+    run_scene(engine, name, scene, frames, &camera_path, logger);
+}
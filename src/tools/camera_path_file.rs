@@ -0,0 +1,75 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use crate::core::{
+    camera_path::{CameraPath, Interpolation, Keyframe},
+    math::vector::Vec3,
+};
+
+/*
+ * Angles are given in degrees, `look_at` replaces yaw and pitch
+ */
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeDescription {
+    time: f32,
+    position: [f32; 3],
+    #[serde(default)]
+    yaw: f32,
+    #[serde(default)]
+    pitch: f32,
+    look_at: Option<[f32; 3]>,
+    fov: Option<f32>,
+    handle_in: Option<[f32; 3]>,
+    handle_out: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraPathDescription {
+    interpolation: Option<String>,
+    #[serde(default)]
+    looping: bool,
+    keyframes: Vec<KeyframeDescription>,
+}
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn to_vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+/*
+ * Read camera keyframes from a TOML file, times are in seconds
+ */
+pub fn read_camera_path_file(path: &Path) -> Result<CameraPath> {
+    let content = fs::read_to_string(path)?;
+    let description: CameraPathDescription = toml::from_str(&content)
+        .map_err(|e| invalid_data(format!("Invalid camera path file {:?}: {}", path, e)))?;
+    let interpolation = match &description.interpolation {
+        Some(interpolation) => interpolation.parse::<Interpolation>().map_err(invalid_data)?,
+        None => Interpolation::default(),
+    };
+    let keyframes = description.keyframes.iter()
+        .map(|keyframe| {
+            let position = to_vec3(keyframe.position);
+            let mut result = match keyframe.look_at {
+                Some(target) => Keyframe::looking_at(keyframe.time, position, to_vec3(target)),
+                None => Keyframe::new(keyframe.time, position, keyframe.yaw.to_radians(), keyframe.pitch.to_radians()),
+            };
+            result.fov = keyframe.fov;
+            result.handle_in = keyframe.handle_in.map(to_vec3);
+            result.handle_out = keyframe.handle_out.map(to_vec3);
+            result
+        })
+        .collect();
+    CameraPath::new(keyframes, interpolation, description.looping)
+        .map_err(|e| invalid_data(format!("Invalid camera path file {:?}: {}", path, e)))
+}
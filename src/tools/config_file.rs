@@ -0,0 +1,341 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::core::{
+    bindings::{Action, KeyBindings, Layout},
+    engine::Engine,
+    hud::{HudCorner, HudItem},
+    profiler::Profiler,
+    projection::Projection,
+};
+
+use super::{
+    key_bindings_file::{read_key_bindings_file, rebind, KeyList},
+    scene_file::RenderSettings,
+};
+
+pub const CONFIG_FILE_NAME: &str = "engine_3d.toml";
+
+/*
+ * Size of the view in cells (the terminal size without it) and pace of the loop
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DisplaySettings {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    #[serde(default, serialize_with = "serialize_f32")]
+    pub fps: Option<f32>,
+    #[serde(default, serialize_with = "serialize_f32")]
+    pub tick_rate: Option<f32>,
+    pub variable_step: Option<bool>,
+    pub hud: Option<bool>,
+    pub hud_items: Option<Vec<String>>,
+    pub hud_corner: Option<String>,
+}
+
+/*
+ * Camera placed by the engine when the scene has none, and clipping distances
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraSettings {
+    // "perspective:<fov>" or "orthographic:<view height>"
+    pub projection: Option<String>,
+    // vertical field of view in degrees, with a perspective
+    #[serde(default, serialize_with = "serialize_f32")]
+    pub fov: Option<f32>,
+    #[serde(default, serialize_with = "serialize_f32")]
+    pub near: Option<f32>,
+    #[serde(default, serialize_with = "serialize_f32")]
+    pub far: Option<f32>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControlSettings {
+    pub layout: Option<String>,
+    // key bindings file, applied before `bindings`
+    pub keys: Option<String>,
+    pub bindings: Option<BTreeMap<String, KeyList>>,
+    pub mouse: Option<bool>,
+    #[serde(default, serialize_with = "serialize_f32")]
+    pub mouse_sensitivity: Option<f32>,
+    pub invert_y: Option<bool>,
+    #[serde(default, serialize_with = "serialize_f32")]
+    pub move_speed: Option<f32>,
+    #[serde(default, serialize_with = "serialize_f32")]
+    pub acceleration: Option<f32>,
+    #[serde(default, serialize_with = "serialize_f32")]
+    pub turn_speed: Option<f32>,
+    // milliseconds without repeat releasing a key, when the terminal does not report the releases
+    pub key_timeout: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogSettings {
+    // false writes nothing, true writes the debug messages
+    pub enabled: Option<bool>,
+    // "warn", "debug,tools::wavefront=trace"..., applied after `enabled`
+    pub level: Option<String>,
+    pub path: Option<String>,
+    // bytes before the file is rotated, 0 never
+    pub max_size: Option<u64>,
+    // rotated files kept
+    pub keep: Option<usize>,
+}
+
+/*
+ * Stage timers, summarized on exit
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileSettings {
+    pub enabled: Option<bool>,
+    // Chrome trace-event file, enables the profiler
+    pub trace: Option<String>,
+}
+
+/*
+ * Settings of the engine, unset values keep the default ones.
+ * The scene file and the command line options override them.
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub display: DisplaySettings,
+    #[serde(default)]
+    pub camera: CameraSettings,
+    #[serde(default)]
+    pub render: RenderSettings,
+    #[serde(default)]
+    pub controls: ControlSettings,
+    #[serde(default)]
+    pub log: LogSettings,
+    #[serde(default)]
+    pub profile: ProfileSettings,
+}
+
+/*
+ * Shortest decimal form of the f32, 0.1 instead of 0.10000000149011612
+ */
+fn serialize_f32<S: Serializer>(value: &Option<f32>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.serialize_some(&value.to_string().parse::<f64>().unwrap_or(*value as f64)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/*
+ * `engine_3d.toml` of the current directory, or of the user configuration directory
+ * ($XDG_CONFIG_HOME/engine_3d, ~/.config/engine_3d)
+ */
+pub fn find_config_file() -> Option<PathBuf> {
+    let local = PathBuf::from(CONFIG_FILE_NAME);
+    if local.is_file() {
+        return Some(local);
+    }
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    let path = config_home.join("engine_3d").join(CONFIG_FILE_NAME);
+    path.is_file().then_some(path)
+}
+
+pub fn read_config_file(path: &Path) -> Result<ConfigFile> {
+    let content = fs::read_to_string(path)?;
+    toml::from_str(&content).map_err(|e| invalid_data(format!("Invalid config file {:?}: {}", path, e)))
+}
+
+impl ConfigFile {
+    /*
+     * Size of the view given by the file, the other dimension from the terminal
+     */
+    pub fn view_size(&self, terminal: (usize, usize)) -> (usize, usize) {
+        (
+            self.display.width.unwrap_or(terminal.0),
+            self.display.height.unwrap_or(terminal.1),
+        )
+    }
+
+    /*
+     * Apply the settings on the engine, except the view size and the log given at its creation
+     */
+    pub fn apply(&self, engine: &mut Engine) -> Result<()> {
+        let display = &self.display;
+        if display.width.is_some() || display.height.is_some() {
+            engine.follow_terminal = false;
+        }
+        if let Some(fps) = display.fps {
+            engine.timing.target_fps = fps;
+        }
+        if let Some(tick_rate) = display.tick_rate {
+            engine.timing.tick_rate = tick_rate;
+        }
+        if let Some(variable_step) = display.variable_step {
+            engine.timing.variable_step = variable_step;
+        }
+        engine.timing.check().map_err(invalid_data)?;
+        if let Some(hud) = display.hud {
+            engine.hud.visible = hud;
+        }
+        if let Some(items) = &display.hud_items {
+            engine.hud.items = items
+                .iter()
+                .map(|item| item.parse::<HudItem>())
+                .collect::<std::result::Result<_, _>>()
+                .map_err(invalid_data)?;
+        }
+        if let Some(corner) = &display.hud_corner {
+            engine.hud.corner = corner.parse::<HudCorner>().map_err(invalid_data)?;
+        }
+
+        let camera = &self.camera;
+        if let Some(projection) = &camera.projection {
+            engine.projection = projection.parse::<Projection>().map_err(invalid_data)?;
+        }
+        if let Some(fov) = camera.fov {
+            engine.projection = engine.projection.with_fov(fov).map_err(invalid_data)?;
+        }
+        if let Some(near) = camera.near {
+            engine.z_near = near;
+        }
+        if let Some(far) = camera.far {
+            engine.z_far = far;
+        }
+        if engine.z_near <= 0.0 || engine.z_far <= engine.z_near {
+            return Err(invalid_data(format!(
+                "The clipping distances must verify 0 < near < far, not near={} far={}",
+                engine.z_near, engine.z_far
+            )));
+        }
+
+        self.render.apply(engine).map_err(invalid_data)?;
+
+        let controls = &self.controls;
+        let layout = match &controls.layout {
+            Some(layout) => Some(layout.parse::<Layout>().map_err(invalid_data)?),
+            None => None,
+        };
+        match &controls.keys {
+            Some(path) => engine.controls.bindings = read_key_bindings_file(Path::new(path), layout.unwrap_or_default())?,
+            None => {
+                if let Some(layout) = layout {
+                    engine.controls.bindings = KeyBindings::profile(layout);
+                }
+            }
+        }
+        if let Some(bindings) = &controls.bindings {
+            rebind(&mut engine.controls.bindings, bindings)?;
+        }
+        let mouse = &mut engine.controls.mouse;
+        if let Some(enabled) = controls.mouse {
+            mouse.enabled = enabled;
+        }
+        if let Some(sensitivity) = controls.mouse_sensitivity {
+            mouse.sensitivity = sensitivity;
+        }
+        if let Some(invert_y) = controls.invert_y {
+            mouse.invert_y = invert_y;
+        }
+        let movement = &mut engine.controls.movement;
+        if let Some(speed) = controls.move_speed {
+            movement.speed = speed;
+        }
+        if let Some(acceleration) = controls.acceleration {
+            movement.acceleration = acceleration;
+        }
+        if let Some(turn_speed) = controls.turn_speed {
+            movement.turn_speed = turn_speed;
+        }
+        if let Some(key_timeout) = controls.key_timeout {
+            movement.key_timeout = Duration::from_millis(key_timeout);
+        }
+
+        let profile = &self.profile;
+        if profile.enabled.is_some() || profile.trace.is_some() {
+            let trace_path = profile.trace.as_ref().map(PathBuf::from);
+            engine.profiler = Arc::new(Profiler::new(profile.enabled.unwrap_or(true), trace_path));
+        }
+        Ok(())
+    }
+
+    /*
+     * Every setting of the engine, as read back by `apply`
+     */
+    pub fn effective(engine: &Engine, log: LogSettings) -> ConfigFile {
+        let bindings = &engine.controls.bindings;
+        let mouse = &engine.controls.mouse;
+        let movement = &engine.controls.movement;
+        ConfigFile {
+            display: DisplaySettings {
+                width: Some(engine.width),
+                height: Some(engine.height),
+                fps: Some(engine.timing.target_fps),
+                tick_rate: Some(engine.timing.tick_rate),
+                variable_step: Some(engine.timing.variable_step),
+                hud: Some(engine.hud.visible),
+                hud_items: Some(engine.hud.items.iter().map(HudItem::to_string).collect()),
+                hud_corner: Some(engine.hud.corner.to_string()),
+            },
+            camera: CameraSettings {
+                projection: Some(engine.projection.to_string()),
+                fov: None,
+                near: Some(engine.z_near),
+                // TOML has no infinity for the serializer, the far plane is left unset
+                far: engine.z_far.is_finite().then_some(engine.z_far),
+            },
+            render: RenderSettings {
+                mode: Some(engine.render_mode.to_string()),
+                color_mode: Some(engine.color_mode.to_string()),
+                texture_filter: Some(engine.texture_filter.to_string()),
+                threads: Some(engine.threads),
+                face_culling: Some(engine.face_culling),
+                shading_ramp: Some(engine.shading_ramp.iter().collect()),
+            },
+            controls: ControlSettings {
+                layout: None,
+                keys: None,
+                bindings: Some(
+                    Action::all()
+                        .map(|action| {
+                            let keys = bindings.keys(action).iter().map(|key| key.to_string()).collect();
+                            (action.to_string(), KeyList::Several(keys))
+                        })
+                        .collect(),
+                ),
+                mouse: Some(mouse.enabled),
+                mouse_sensitivity: Some(mouse.sensitivity),
+                invert_y: Some(mouse.invert_y),
+                move_speed: Some(movement.speed),
+                acceleration: Some(movement.acceleration),
+                turn_speed: Some(movement.turn_speed),
+                key_timeout: Some(movement.key_timeout.as_millis() as u64),
+            },
+            log,
+            profile: ProfileSettings {
+                enabled: Some(engine.profiler.enabled),
+                trace: engine.profiler.trace_path.as_ref().map(|path| path.to_string_lossy().to_string()),
+            },
+        }
+    }
+
+    pub fn to_toml(&self) -> std::result::Result<String, String> {
+        toml::to_string(self).map_err(|e| e.to_string())
+    }
+}
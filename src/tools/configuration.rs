@@ -10,15 +10,15 @@ impl Configuration {
     if let Some((w, h)) = term_size::dimensions() {
       Configuration {
         width: w,
-        height: h - 0,
-        buffer_size: w * (h - 0)
+        height: h,
+        buffer_size: w * h
       }
     } else {
       // panic!("Unable to get term size !")
       Configuration {
         width: 700,
-        height: 500 - 0,
-        buffer_size: 700 * (500 - 0)
+        height: 500,
+        buffer_size: 700 * 500
       }
     }
   }
@@ -0,0 +1,1151 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+
+use crate::core::{
+    engine::{Camera, LightSource},
+    material::Material,
+    math::{
+        matrix::Mat4,
+        quaternion::Quat,
+        triangle::Triangle3D,
+        vector::{Vec2, Vec3},
+    },
+    mesh::{Mesh, Point},
+    projection::Projection,
+    scene::{Node, Scene, Transform},
+    texture::{Texture, TextureWrap},
+};
+
+use super::{
+    image::{read_image_file, read_png_data},
+    logger::Logger,
+    mesh_file::LoadError,
+};
+use crate::{log_debug, log_warn};
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F534A;
+const CHUNK_BIN: u32 = 0x004E4942;
+const PNG_SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+const MODE_POINTS: u32 = 0;
+const MODE_TRIANGLES: u32 = 4;
+const MODE_TRIANGLE_STRIP: u32 = 5;
+const MODE_TRIANGLE_FAN: u32 = 6;
+const WRAP_CLAMP_TO_EDGE: u32 = 33071;
+
+// the engine lights are points, a directional light is placed that far against its direction
+const DIRECTIONAL_LIGHT_DISTANCE: f32 = 1e4;
+
+// extensions the scene can be read with
+const SUPPORTED_EXTENSIONS: [&str; 1] = ["KHR_lights_punctual"];
+
+/*
+ * Subset of the glTF 2.0 document read by the importer, the other properties are ignored
+ */
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Document {
+    asset: Asset,
+    scene: Option<usize>,
+    scenes: Vec<SceneDef>,
+    nodes: Vec<NodeDef>,
+    meshes: Vec<MeshDef>,
+    accessors: Vec<Accessor>,
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+    materials: Vec<MaterialDef>,
+    textures: Vec<TextureDef>,
+    images: Vec<ImageDef>,
+    samplers: Vec<Sampler>,
+    cameras: Vec<CameraDef>,
+    extensions: DocumentExtensions,
+    extensions_required: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Asset {
+    version: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SceneDef {
+    nodes: Vec<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NodeDef {
+    name: Option<String>,
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    camera: Option<usize>,
+    // column-major
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    // quaternion x, y, z, w
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+    extensions: NodeExtensions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NodeExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    light: Option<LightRef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct LightRef {
+    light: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DocumentExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    lights: LightsDef,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct LightsDef {
+    lights: Vec<LightDef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct LightDef {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MeshDef {
+    name: Option<String>,
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    mode: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Accessor {
+    buffer_view: Option<usize>,
+    byte_offset: usize,
+    component_type: u32,
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<Sparse>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Sparse {
+    count: usize,
+    indices: SparseIndices,
+    values: SparseValues,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SparseIndices {
+    buffer_view: usize,
+    byte_offset: usize,
+    component_type: u32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SparseValues {
+    buffer_view: usize,
+    byte_offset: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct BufferView {
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Buffer {
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct MaterialDef {
+    name: Option<String>,
+    pbr_metallic_roughness: PbrDef,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct PbrDef {
+    base_color_factor: Option<[f32; 4]>,
+    base_color_texture: Option<TextureInfo>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct TextureInfo {
+    index: usize,
+    tex_coord: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TextureDef {
+    source: Option<usize>,
+    sampler: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct ImageDef {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Sampler {
+    wrap_s: Option<u32>,
+    wrap_t: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CameraDef {
+    #[serde(rename = "type")]
+    kind: String,
+    perspective: Option<PerspectiveDef>,
+    orthographic: Option<OrthographicDef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PerspectiveDef {
+    // radians
+    yfov: f32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct OrthographicDef {
+    ymag: f32,
+}
+
+/*
+ * Read a glTF 2.0 scene, JSON (.gltf) or binary (.glb), with its buffers embedded in base64
+ * or in files next to it. The node hierarchy, the meshes, the base colour of the materials,
+ * the first camera and the KHR_lights_punctual lights are kept. The engine lights being white
+ * points, the colour, intensity, range and cone of the lights are left out.
+ */
+pub fn read_gltf_file(path: &Path, logger: &Logger) -> Result<Scene, LoadError> {
+    let data = fs::read(path)?;
+    let (json, bin) = if data.starts_with(GLB_MAGIC) {
+        parse_glb(path, &data)?
+    } else {
+        (&data[..], None)
+    };
+    let document: Document =
+        serde_json::from_slice(json).map_err(|e| LoadError::parse(path, None, format!("Invalid glTF JSON: {}", e)))?;
+    if !document.asset.version.starts_with("2.") {
+        return Err(LoadError::parse(
+            path,
+            None,
+            format!("Unsupported glTF version '{}', 2.x expected", document.asset.version),
+        ));
+    }
+    if let Some(extension) = document
+        .extensions_required
+        .iter()
+        .find(|extension| !SUPPORTED_EXTENSIONS.contains(&extension.as_str()))
+    {
+        return Err(LoadError::parse(
+            path,
+            None,
+            format!("Unsupported required extension {}", extension),
+        ));
+    }
+    let buffers = document
+        .buffers
+        .iter()
+        .enumerate()
+        .map(|(index, buffer)| load_buffer(path, index, buffer, bin))
+        .collect::<Result<Vec<_>, _>>()?;
+    let loader = Loader {
+        path,
+        document: &document,
+        buffers,
+        logger,
+    };
+    loader.scene()
+}
+
+/*
+ * JSON chunk and BIN chunk of a binary glTF: a 12 bytes header "glTF", version, length,
+ * then chunks of length, type and data
+ */
+fn parse_glb<'a>(path: &Path, data: &'a [u8]) -> Result<(&'a [u8], Option<&'a [u8]>), LoadError> {
+    let error = |message: &str| LoadError::parse(path, None, message);
+    let read_u32 = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or_else(|| error("Truncated binary glTF"))
+    };
+    let version = read_u32(4)?;
+    if version != 2 {
+        return Err(LoadError::parse(
+            path,
+            None,
+            format!("Unsupported binary glTF version {}", version),
+        ));
+    }
+    let length = read_u32(8)? as usize;
+    if length > data.len() {
+        return Err(error("Truncated binary glTF"));
+    }
+    let (mut json, mut bin) = (None, None);
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(offset)? as usize;
+        let kind = read_u32(offset + 4)?;
+        let chunk = data
+            .get(offset + 8..offset + 8 + chunk_length)
+            .filter(|_| offset + 8 + chunk_length <= length)
+            .ok_or_else(|| error("Truncated binary glTF chunk"))?;
+        // the first chunk of each type counts, the unknown ones are skipped
+        match kind {
+            CHUNK_JSON => _ = json.get_or_insert(chunk),
+            CHUNK_BIN => _ = bin.get_or_insert(chunk),
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+    Ok((json.ok_or_else(|| error("Binary glTF without JSON chunk"))?, bin))
+}
+
+/*
+ * Content of a buffer: the BIN chunk of a .glb when it has no uri
+ */
+fn load_buffer(path: &Path, index: usize, buffer: &Buffer, bin: Option<&[u8]>) -> Result<Vec<u8>, LoadError> {
+    let data = match &buffer.uri {
+        Some(uri) => read_uri(path, uri)?,
+        None => bin
+            .map(|bin| bin.to_vec())
+            .ok_or_else(|| LoadError::parse(path, None, format!("Buffer {} has no uri", index)))?,
+    };
+    if data.len() < buffer.byte_length {
+        return Err(LoadError::parse(
+            path,
+            None,
+            format!(
+                "Buffer {}: {} bytes expected, {} found",
+                index,
+                buffer.byte_length,
+                data.len()
+            ),
+        ));
+    }
+    Ok(data)
+}
+
+/*
+ * Bytes of a base64 data uri or of a file relative to the glTF file, never fetched from the network
+ */
+fn read_uri(path: &Path, uri: &str) -> Result<Vec<u8>, LoadError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        return match data.split_once(',') {
+            Some((header, payload)) if header.ends_with(";base64") => {
+                decode_base64(payload).ok_or_else(|| LoadError::parse(path, None, "Invalid base64 in a data uri"))
+            }
+            _ => Err(LoadError::parse(path, None, "Only base64 data uris are supported")),
+        };
+    }
+    if uri.contains("://") {
+        return Err(LoadError::parse(
+            path,
+            None,
+            format!("Only local files can be read, not {}", uri),
+        ));
+    }
+    let file = uri_path(path, uri);
+    fs::read(&file).map_err(|e| LoadError::Io(io::Error::new(e.kind(), format!("{}: {}", file.display(), e))))
+}
+
+fn uri_path(path: &Path, uri: &str) -> PathBuf {
+    path.parent().unwrap_or(Path::new("")).join(percent_decode(uri))
+}
+
+/*
+ * Standard or URL-safe base64, padding optional
+ */
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/*
+ * "%20" and the like in the file names of the uris
+ */
+fn percent_decode(uri: &str) -> String {
+    let mut bytes = Vec::with_capacity(uri.len());
+    let mut i = 0;
+    while i < uri.len() {
+        let escaped = (uri.as_bytes()[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(uri.as_bytes()[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn component_size(component_type: u32) -> Option<usize> {
+    match component_type {
+        5120 | 5121 => Some(1),
+        5122 | 5123 => Some(2),
+        5125 | 5126 => Some(4),
+        _ => None,
+    }
+}
+
+/*
+ * Value of a component, the normalized integers mapped on [0, 1] or [-1, 1]
+ */
+fn read_component(component_type: u32, normalized: bool, bytes: &[u8]) -> f64 {
+    let (value, max) = match component_type {
+        5120 => (bytes[0] as i8 as f64, 127.0),
+        5121 => (bytes[0] as f64, 255.0),
+        5122 => (i16::from_le_bytes([bytes[0], bytes[1]]) as f64, 32767.0),
+        5123 => (u16::from_le_bytes([bytes[0], bytes[1]]) as f64, 65535.0),
+        5125 => (
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            4294967295.0,
+        ),
+        _ => return f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+    };
+    if normalized {
+        (value / max).max(-1.0)
+    } else {
+        value
+    }
+}
+
+fn component_count(kind: &str) -> Option<usize> {
+    match kind {
+        "SCALAR" => Some(1),
+        "VEC2" => Some(2),
+        "VEC3" => Some(3),
+        "VEC4" => Some(4),
+        _ => None,
+    }
+}
+
+/*
+ * Local matrix of a node, given as a matrix or as translation, rotation and scale
+ */
+fn node_matrix(node: &NodeDef) -> Mat4 {
+    if let Some(matrix) = node.matrix {
+        let mut m = Mat4::identity().m;
+        for (col, column) in matrix.chunks_exact(4).enumerate() {
+            for (row, value) in column.iter().enumerate() {
+                m[row][col] = *value;
+            }
+        }
+        return Mat4 { m };
+    }
+    let [tx, ty, tz] = node.translation.unwrap_or([0.0; 3]);
+    let [x, y, z, w] = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let [sx, sy, sz] = node.scale.unwrap_or([1.0; 3]);
+    Mat4::translation(Vec3::new(tx, ty, tz))
+        * Mat4::rotation(Quat { w, x, y, z }.normalize())
+        * Mat4::scaling(Vec3::new(sx, sy, sz))
+}
+
+struct Loader<'a> {
+    path: &'a Path,
+    document: &'a Document,
+    buffers: Vec<Vec<u8>>,
+    logger: &'a Logger,
+}
+
+impl Loader<'_> {
+    fn error(&self, message: impl Into<String>) -> LoadError {
+        LoadError::parse(self.path, None, message)
+    }
+
+    /*
+     * Nodes of the scene, each one after its parent, then the camera and the lights placed in the world
+     */
+    fn scene(&self) -> Result<Scene, LoadError> {
+        let document = self.document;
+        let roots: Vec<usize> = match document.scene.or((!document.scenes.is_empty()).then_some(0)) {
+            Some(index) => {
+                let scene = document
+                    .scenes
+                    .get(index)
+                    .ok_or_else(|| self.error(format!("Unknown scene {}", index)))?;
+                scene.nodes.clone()
+            }
+            // without scenes, every node which is no one's child
+            None => (0..document.nodes.len())
+                .filter(|index| !document.nodes.iter().any(|node| node.children.contains(index)))
+                .collect(),
+        };
+        let materials = self.materials();
+        let mut scene = Scene::new();
+        let mut meshes: Vec<Option<usize>> = vec![None; document.meshes.len()];
+        let mut visited = vec![false; document.nodes.len()];
+        let mut cameras: Vec<(usize, usize)> = Vec::new();
+        let mut lights: Vec<(usize, usize)> = Vec::new();
+        let mut stack: Vec<(usize, Option<usize>)> = roots.iter().rev().map(|&root| (root, None)).collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = document
+                .nodes
+                .get(index)
+                .ok_or_else(|| self.error(format!("Unknown node {}", index)))?;
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(self.error(format!("Node {} appears twice in the hierarchy", index)));
+            }
+            let mesh = match node.mesh {
+                Some(mesh) => Some(match meshes.get(mesh).copied() {
+                    Some(Some(loaded)) => loaded,
+                    Some(None) => {
+                        let loaded = scene.add_mesh(self.mesh(mesh, &materials)?);
+                        meshes[mesh] = Some(loaded);
+                        loaded
+                    }
+                    None => return Err(self.error(format!("Node {} references the unknown mesh {}", index, mesh))),
+                }),
+                None => None,
+            };
+            let name = node
+                .name
+                .clone()
+                .or_else(|| node.mesh.and_then(|mesh| document.meshes[mesh].name.clone()))
+                .unwrap_or_else(|| format!("node {}", index));
+            let added = scene.add_node(Node {
+                transform: Transform::from_matrix(&node_matrix(node)),
+                parent,
+                ..Node::new(&name, mesh)
+            });
+            if let Some(camera) = node.camera {
+                cameras.push((added, camera));
+            }
+            if let Some(light) = &node.extensions.light {
+                lights.push((added, light.light));
+            }
+            stack.extend(node.children.iter().rev().map(|&child| (child, Some(added))));
+        }
+
+        let world = scene.world_transforms();
+        // the glTF cameras and lights look down their -Z axis
+        let place = |node: usize| {
+            let position = world[node].transform_point(Vec3::new(0.0, 0.0, 0.0));
+            (position, world[node].transform_vector(Vec3::new(0.0, 0.0, -1.0)))
+        };
+        if let Some(&(node, camera)) = cameras.first() {
+            let camera = document
+                .cameras
+                .get(camera)
+                .ok_or_else(|| self.error(format!("Unknown camera {}", camera)))?;
+            let (position, direction) = place(node);
+            let mut cam = Camera::new(position, 0.0, 0.0, self.projection(camera));
+            cam.look_at(position + direction);
+            scene.camera = Some(cam);
+        }
+        for &(node, light) in &lights {
+            let definition = document
+                .extensions
+                .lights
+                .lights
+                .get(light)
+                .ok_or_else(|| self.error(format!("Unknown light {}", light)))?;
+            let (position, direction) = place(node);
+            match definition.kind.as_str() {
+                "point" | "spot" => scene.lights.push(LightSource::at(&position)),
+                "directional" if direction.length2() > 0.0 => {
+                    let position = position - direction.normalize() * DIRECTIONAL_LIGHT_DISTANCE;
+                    scene.lights.push(LightSource::at(&position));
+                }
+                kind => log_warn!(
+                    self.logger,
+                    "{:?}: light '{}' of type '{}' skipped",
+                    self.path,
+                    definition.name.as_deref().unwrap_or_default(),
+                    kind
+                ),
+            }
+        }
+        log_debug!(
+            self.logger,
+            "{:?}: {} nodes, {} meshes, {} cameras, {} lights",
+            self.path,
+            scene.nodes.len(),
+            scene.meshes().len(),
+            cameras.len(),
+            scene.lights.len()
+        );
+        Ok(scene)
+    }
+
+    fn projection(&self, camera: &CameraDef) -> Projection {
+        let projection = match (camera.kind.as_str(), &camera.perspective, &camera.orthographic) {
+            ("perspective", Some(perspective), _) => Projection::Perspective {
+                fov: perspective.yfov.to_degrees(),
+            },
+            ("orthographic", _, Some(orthographic)) => Projection::Orthographic {
+                height: 2.0 * orthographic.ymag.abs(),
+            },
+            _ => Projection::default(),
+        };
+        match projection {
+            Projection::Perspective { fov } if !(fov > 0.0 && fov < 180.0) => {
+                log_warn!(
+                    self.logger,
+                    "{:?}: invalid field of view {}, the default one is used",
+                    self.path,
+                    fov
+                );
+                Projection::default()
+            }
+            projection => projection,
+        }
+    }
+
+    /*
+     * Engine material of each glTF one, a texture which can't be read is left out
+     */
+    fn materials(&self) -> Vec<Material> {
+        let mut textures: HashMap<usize, Option<Arc<Texture>>> = HashMap::new();
+        self.document
+            .materials
+            .iter()
+            .enumerate()
+            .map(|(index, definition)| {
+                let name = definition.name.clone().unwrap_or_else(|| format!("material {}", index));
+                let mut material = Material::new(&name);
+                let pbr = &definition.pbr_metallic_roughness;
+                if let Some([r, g, b, _]) = pbr.base_color_factor {
+                    material.diffuse = Vec3::new(r, g, b);
+                }
+                if let Some(info) = &pbr.base_color_texture {
+                    if info.tex_coord != 0 {
+                        log_warn!(
+                            self.logger,
+                            "{:?}: material '{}' uses TEXCOORD_{}, read as TEXCOORD_0",
+                            self.path,
+                            name,
+                            info.tex_coord
+                        );
+                    }
+                    material.texture = textures
+                        .entry(info.index)
+                        .or_insert_with(|| match self.texture(info.index) {
+                            Ok(texture) => Some(Arc::new(texture)),
+                            Err(e) => {
+                                log_warn!(
+                                    self.logger,
+                                    "{:?}: unable to read the texture {}: {}",
+                                    self.path,
+                                    info.index,
+                                    e
+                                );
+                                None
+                            }
+                        })
+                        .clone();
+                }
+                material
+            })
+            .collect()
+    }
+
+    fn texture(&self, index: usize) -> Result<Texture, LoadError> {
+        let texture = self
+            .document
+            .textures
+            .get(index)
+            .ok_or_else(|| self.error("Unknown texture"))?;
+        let source = texture.source.ok_or_else(|| self.error("Texture without image"))?;
+        let image = self
+            .document
+            .images
+            .get(source)
+            .ok_or_else(|| self.error("Unknown image"))?;
+        let mut texture_image = match (&image.uri, image.buffer_view) {
+            (Some(uri), _) if !uri.starts_with("data:") => read_image_file(&uri_path(self.path, uri))?,
+            (Some(uri), _) => self.decode_image(&read_uri(self.path, uri)?)?,
+            (None, Some(view)) => self.decode_image(self.view_data(view)?)?,
+            (None, None) => return Err(self.error("Image without uri nor buffer view")),
+        };
+        if let Some(sampler) = texture.sampler {
+            let sampler = self
+                .document
+                .samplers
+                .get(sampler)
+                .ok_or_else(|| self.error("Unknown sampler"))?;
+            // a single wrap mode for both axes in the engine
+            if sampler.wrap_s == Some(WRAP_CLAMP_TO_EDGE) && sampler.wrap_t == Some(WRAP_CLAMP_TO_EDGE) {
+                texture_image.wrap = TextureWrap::Clamp;
+            }
+        }
+        Ok(texture_image)
+    }
+
+    fn decode_image(&self, data: &[u8]) -> Result<Texture, LoadError> {
+        if data.starts_with(PNG_SIGNATURE) {
+            Ok(read_png_data(data)?)
+        } else {
+            Err(self.error("Only PNG images can be embedded"))
+        }
+    }
+
+    fn view_data(&self, index: usize) -> Result<&[u8], LoadError> {
+        let view = self
+            .document
+            .buffer_views
+            .get(index)
+            .ok_or_else(|| self.error(format!("Unknown buffer view {}", index)))?;
+        self.buffers
+            .get(view.buffer)
+            .and_then(|buffer| buffer.get(view.byte_offset..view.byte_offset.checked_add(view.byte_length)?))
+            .ok_or_else(|| self.error(format!("Buffer view {} out of its buffer", index)))
+    }
+
+    /*
+     * Values of the accessor element by element, with the number of components of an element
+     */
+    fn accessor(&self, index: usize) -> Result<(Vec<f64>, usize), LoadError> {
+        let accessor = self
+            .document
+            .accessors
+            .get(index)
+            .ok_or_else(|| self.error(format!("Unknown accessor {}", index)))?;
+        let error = |message: &str| self.error(format!("Accessor {}: {}", index, message));
+        let components = component_count(&accessor.kind).ok_or_else(|| error("unsupported type"))?;
+        let size = component_size(accessor.component_type).ok_or_else(|| error("unknown component type"))?;
+        let element_size = components * size;
+        let read_element = |element: &[u8], values: &mut Vec<f64>| {
+            for component in element.chunks_exact(size) {
+                values.push(read_component(accessor.component_type, accessor.normalized, component));
+            }
+        };
+        // the count comes from the file: the size is checked before allocating
+        let length = accessor
+            .count
+            .checked_mul(element_size)
+            .ok_or_else(|| error("too many elements"))?;
+        let mut values = match accessor.buffer_view {
+            Some(view) => {
+                let data = self.view_data(view)?;
+                let stride = self.document.buffer_views[view].byte_stride.unwrap_or(element_size);
+                let end = accessor
+                    .count
+                    .checked_sub(1)
+                    .map_or(Some(0), |last| last.checked_mul(stride)?.checked_add(element_size))
+                    .and_then(|end| end.checked_add(accessor.byte_offset));
+                if end.is_none_or(|end| end > data.len()) {
+                    return Err(error("out of its buffer view"));
+                }
+                let mut values = Vec::with_capacity(accessor.count * components);
+                for i in 0..accessor.count {
+                    let start = accessor.byte_offset + i * stride;
+                    read_element(&data[start..start + element_size], &mut values);
+                }
+                values
+            }
+            // zeros, replaced by the sparse values, no larger than the data of the file
+            None => {
+                if length > self.buffers.iter().map(Vec::len).sum() {
+                    return Err(error("too many elements for the buffers of the file"));
+                }
+                vec![0.0; accessor.count * components]
+            }
+        };
+        if let Some(sparse) = &accessor.sparse {
+            let index_size =
+                component_size(sparse.indices.component_type).ok_or_else(|| error("unknown sparse index type"))?;
+            let indices = self
+                .view_data(sparse.indices.buffer_view)?
+                .get(sparse.indices.byte_offset..);
+            let replacements = self
+                .view_data(sparse.values.buffer_view)?
+                .get(sparse.values.byte_offset..);
+            let (Some(indices), Some(replacements)) = (indices, replacements) else {
+                return Err(error("sparse values out of their buffer view"));
+            };
+            let indices = indices.chunks_exact(index_size).take(sparse.count);
+            let replacements = replacements.chunks_exact(element_size).take(sparse.count);
+            if indices.len() < sparse.count || replacements.len() < sparse.count {
+                return Err(error("sparse values out of their buffer view"));
+            }
+            for (element_index, element) in indices.zip(replacements) {
+                let element_index = read_component(sparse.indices.component_type, false, element_index) as usize;
+                if element_index >= accessor.count {
+                    return Err(error("sparse index out of the accessor"));
+                }
+                let mut replaced = Vec::with_capacity(components);
+                read_element(element, &mut replaced);
+                values[element_index * components..(element_index + 1) * components].copy_from_slice(&replaced);
+            }
+        }
+        Ok((values, components))
+    }
+
+    /*
+     * Values of an attribute, which must have one of the `expected` numbers of components
+     */
+    fn attribute(
+        &self,
+        primitive: &Primitive,
+        name: &str,
+        expected: &[usize],
+        count: Option<usize>,
+    ) -> Result<Option<(Vec<f64>, usize)>, LoadError> {
+        let Some(&index) = primitive.attributes.get(name) else {
+            return Ok(None);
+        };
+        let (values, components) = self.accessor(index)?;
+        if !expected.contains(&components) {
+            return Err(self.error(format!("Attribute {} of {} components", name, components)));
+        }
+        if count.is_some_and(|count| values.len() != count * components) {
+            return Err(self.error(format!("Attribute {} doesn't have one value per vertex", name)));
+        }
+        Ok(Some((values, components)))
+    }
+
+    /*
+     * Engine mesh of the primitives of a glTF mesh, with the materials they use
+     */
+    fn mesh(&self, index: usize, materials: &[Material]) -> Result<Mesh, LoadError> {
+        let definition = &self.document.meshes[index];
+        let mut mesh = Mesh::default();
+        // index in the mesh of each glTF material
+        let mut mesh_materials: HashMap<usize, usize> = HashMap::new();
+        for primitive in &definition.primitives {
+            let Some((positions, _)) = self.attribute(primitive, "POSITION", &[3], None)? else {
+                log_warn!(
+                    self.logger,
+                    "{:?}: primitive of the mesh {} without positions skipped",
+                    self.path,
+                    index
+                );
+                continue;
+            };
+            let positions: Vec<Vec3> = positions
+                .chunks_exact(3)
+                .map(|p| Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32))
+                .collect();
+            let count = Some(positions.len());
+            // the glTF images start at the top, the engine textures at the bottom
+            let uvs: Option<Vec<Vec2>> = self.attribute(primitive, "TEXCOORD_0", &[2], count)?.map(|(uvs, _)| {
+                uvs.chunks_exact(2)
+                    .map(|uv| Vec2::new(uv[0] as f32, 1.0 - uv[1] as f32))
+                    .collect()
+            });
+            let colors: Option<Vec<Vec3>> =
+                self.attribute(primitive, "COLOR_0", &[3, 4], count)?
+                    .map(|(colors, components)| {
+                        colors
+                            .chunks_exact(components)
+                            .map(|c| Vec3::new(c[0] as f32, c[1] as f32, c[2] as f32))
+                            .collect()
+                    });
+            let material = match primitive.material {
+                Some(material) if material < materials.len() => {
+                    Some(*mesh_materials.entry(material).or_insert_with(|| {
+                        mesh.materials.push(materials[material].clone());
+                        mesh.materials.len() - 1
+                    }))
+                }
+                Some(material) => return Err(self.error(format!("Unknown material {}", material))),
+                None => None,
+            };
+            let indices: Vec<usize> = match primitive.indices {
+                Some(accessor) => self
+                    .accessor(accessor)?
+                    .0
+                    .into_iter()
+                    .map(|index| index as usize)
+                    .collect(),
+                None => (0..positions.len()).collect(),
+            };
+            if indices.iter().any(|&index| index >= positions.len()) {
+                return Err(self.error(format!("Mesh {}: vertex index out of the primitive", index)));
+            }
+
+            let corners: Vec<[usize; 3]> = match primitive.mode.unwrap_or(MODE_TRIANGLES) {
+                MODE_TRIANGLES => indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+                // every other triangle of a strip is turned back to keep the winding
+                MODE_TRIANGLE_STRIP => indices
+                    .windows(3)
+                    .enumerate()
+                    .map(|(i, t)| {
+                        if i % 2 == 0 {
+                            [t[0], t[1], t[2]]
+                        } else {
+                            [t[1], t[0], t[2]]
+                        }
+                    })
+                    .collect(),
+                MODE_TRIANGLE_FAN => indices.windows(2).skip(1).map(|t| [indices[0], t[0], t[1]]).collect(),
+                MODE_POINTS => {
+                    let normals = self
+                        .attribute(primitive, "NORMAL", &[3], count)?
+                        .map(|(normals, _)| normals);
+                    mesh.points.extend(indices.iter().map(|&i| {
+                        Point {
+                            position: positions[i],
+                            normal: normals
+                                .as_ref()
+                                .map(|n| Vec3::new(n[3 * i] as f32, n[3 * i + 1] as f32, n[3 * i + 2] as f32)),
+                            color: colors.as_ref().map(|colors| colors[i]),
+                        }
+                    }));
+                    continue;
+                }
+                mode => {
+                    log_warn!(
+                        self.logger,
+                        "{:?}: primitive of the mesh {} in mode {} (lines) skipped",
+                        self.path,
+                        index,
+                        mode
+                    );
+                    continue;
+                }
+            };
+            mesh.triangles.extend(corners.into_iter().map(|[a, b, c]| {
+                let mut triangle = Triangle3D::new(positions[a], positions[b], positions[c]).with_material(material);
+                if let Some(uvs) = &uvs {
+                    triangle = triangle.with_uv([uvs[a], uvs[b], uvs[c]]);
+                }
+                if let Some(colors) = &colors {
+                    triangle = triangle.with_colors([colors[a], colors[b], colors[c]]);
+                }
+                triangle
+            }));
+        }
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::test_files::{corners, read_file};
+
+    // a triangle: 3 positions as f32, base64 encoded
+    const POSITIONS: &str = "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA";
+
+    fn read(name: &str, accessor: &str) -> Result<Scene, LoadError> {
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scenes": [{{ "nodes": [0] }}],
+                "nodes": [{{ "mesh": 0 }}],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }} }}] }}],
+                "accessors": [{}],
+                "bufferViews": [{{ "buffer": 0, "byteLength": 36 }}],
+                "buffers": [{{ "byteLength": 36, "uri": "data:application/octet-stream;base64,{}" }}]
+            }}"#,
+            accessor, POSITIONS
+        );
+        read_file(name, "test.gltf", json.as_bytes(), read_gltf_file)
+    }
+
+    #[test]
+    fn accessor_is_read() {
+        let scene = read(
+            "gltf_accessor",
+            r#"{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }"#,
+        )
+        .unwrap();
+        let mesh = scene.baked_mesh();
+        assert_eq!(mesh.triangles.len(), 1);
+        let v2 = mesh.triangles[0].v2;
+        assert_eq!((v2.x, v2.y, v2.z), (1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn accessor_out_of_its_view_is_rejected() {
+        let accessors = [
+            r#"{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3" }"#,
+            r#"{ "bufferView": 0, "byteOffset": 4, "componentType": 5126, "count": 3, "type": "VEC3" }"#,
+            // the size overflows
+            r#"{ "bufferView": 0, "componentType": 5126, "count": 4611686018427387904, "type": "VEC3" }"#,
+            // zeros larger than the data of the file
+            r#"{ "componentType": 5126, "count": 1000000000, "type": "VEC3" }"#,
+        ];
+        for accessor in accessors {
+            assert!(read("gltf_invalid_accessor", accessor).is_err(), "{}", accessor);
+        }
+    }
+
+    #[test]
+    fn hierarchy_camera_lights_and_materials_are_read() {
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "extensionsUsed": ["KHR_lights_punctual"],
+                "extensions": {{ "KHR_lights_punctual": {{ "lights": [{{ "type": "directional" }}, {{ "type": "point" }}] }} }},
+                "scenes": [{{ "nodes": [0] }}],
+                "nodes": [
+                    {{ "name": "root", "matrix": [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 10, 0, 0, 1], "children": [1, 2, 3, 4] }},
+                    {{ "name": "child", "translation": [0, 5, 0], "mesh": 0 }},
+                    {{ "camera": 0, "translation": [0, 0, 3] }},
+                    {{ "extensions": {{ "KHR_lights_punctual": {{ "light": 0 }} }} }},
+                    {{ "translation": [0, 2, 0], "extensions": {{ "KHR_lights_punctual": {{ "light": 1 }} }} }}
+                ],
+                "cameras": [{{ "type": "perspective", "perspective": {{ "yfov": 1.0, "znear": 0.1 }} }}],
+                "materials": [{{ "name": "red", "pbrMetallicRoughness": {{ "baseColorFactor": [1, 0, 0, 1] }} }}],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "material": 0 }}] }}],
+                "accessors": [{{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }}],
+                "bufferViews": [{{ "buffer": 0, "byteLength": 36 }}],
+                "buffers": [{{ "byteLength": 36, "uri": "data:application/octet-stream;base64,{}" }}]
+            }}"#,
+            POSITIONS
+        );
+        let scene = read_file("gltf_scene", "test.gltf", json.as_bytes(), read_gltf_file).unwrap();
+        let names: Vec<(&str, Option<usize>)> = scene
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.parent))
+            .collect();
+        assert_eq!(names[..2], [("root", None), ("child", Some(0))]);
+
+        // the translation of the column-major matrix moves the child
+        let mesh = scene.baked_mesh();
+        assert_eq!(
+            corners(&mesh.triangles[0]),
+            [(10.0, 5.0, 0.0), (11.0, 5.0, 0.0), (10.0, 6.0, 0.0)]
+        );
+        let material = mesh.material(mesh.triangles[0].material).unwrap();
+        assert_eq!(material.name, "red");
+        assert_eq!(
+            (material.diffuse.x, material.diffuse.y, material.diffuse.z),
+            (1.0, 0.0, 0.0)
+        );
+
+        let camera = scene.camera.as_ref().unwrap();
+        assert_eq!(
+            (camera.position.x, camera.position.y, camera.position.z),
+            (10.0, 0.0, 3.0)
+        );
+        assert_eq!(
+            camera.projection.to_string(),
+            format!("perspective:{}", 1.0f32.to_degrees())
+        );
+        let direction = camera.get_look_at_direction();
+        assert!(
+            (direction - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-6,
+            "{:?}",
+            direction
+        );
+
+        // the directional light far behind its node, against its -Z direction
+        let lights: Vec<_> = scene
+            .lights
+            .iter()
+            .map(|light| light.position)
+            .map(|p| (p.x, p.y, p.z))
+            .collect();
+        assert_eq!(lights, [(10.0, 0.0, DIRECTIONAL_LIGHT_DISTANCE), (10.0, 2.0, 0.0)]);
+    }
+
+    #[test]
+    fn glb_chunks_are_read() {
+        let json = br#"{
+            "asset": { "version": "2.0" },
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+            "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+            "buffers": [{ "byteLength": 36 }]
+        }"#;
+        let bin = decode_base64(POSITIONS).unwrap();
+        // the chunks padded to 4 bytes, the JSON with spaces
+        let mut json_chunk = json.to_vec();
+        json_chunk.resize(json.len().next_multiple_of(4), b' ');
+        let mut data = GLB_MAGIC.to_vec();
+        data.extend(2u32.to_le_bytes());
+        data.extend((12 + 8 + json_chunk.len() as u32 + 8 + bin.len() as u32).to_le_bytes());
+        for (kind, chunk) in [(CHUNK_JSON, &json_chunk), (CHUNK_BIN, &bin)] {
+            data.extend((chunk.len() as u32).to_le_bytes());
+            data.extend(kind.to_le_bytes());
+            data.extend(chunk);
+        }
+
+        let (json_read, bin_read) = parse_glb(Path::new("test.glb"), &data).unwrap();
+        assert_eq!((json_read, bin_read), (&json_chunk[..], Some(&bin[..])));
+        let scene = read_file("gltf_glb", "test.glb", &data, read_gltf_file).unwrap();
+        let mesh = scene.baked_mesh();
+        assert_eq!(
+            corners(&mesh.triangles[0]),
+            [(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)]
+        );
+
+        data.truncate(data.len() - 1);
+        assert!(parse_glb(Path::new("test.glb"), &data).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Error, ErrorKind, Read, Result},
+    path::Path,
+};
+
+use crate::core::{math::vector::Vec3, texture::Texture};
+
+/*
+ * Load a texture from a PPM/PGM (binary or ASCII) or a PNG file
+ */
+pub fn read_image_file(path: &Path) -> Result<Texture> {
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+    match extension.as_deref() {
+        Some("png") => read_png(path),
+        Some("ppm") | Some("pgm") | Some("pnm") => read_netpbm(path),
+        _ => Err(Error::new(ErrorKind::InvalidInput, format!("Unsupported image format '{}'", path.display()))),
+    }
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+fn read_png(path: &Path) -> Result<Texture> {
+    decode_png(BufReader::new(File::open(path)?))
+}
+
+/*
+ * Load a texture from a PNG file already in memory (embedded in a glTF file...)
+ */
+pub fn read_png_data(data: &[u8]) -> Result<Texture> {
+    decode_png(data)
+}
+
+fn decode_png<R: Read>(reader: R) -> Result<Texture> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err(invalid_data("Unexpanded indexed PNG")),
+    };
+    let (width, height) = (info.width as usize, info.height as usize);
+    let pixels = buffer[..width * height * channels]
+        .chunks_exact(channels)
+        .map(|pixel| match channels {
+            1 | 2 => Vec3::new(pixel[0] as f32, pixel[0] as f32, pixel[0] as f32) / 255.0,
+            _ => Vec3::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32) / 255.0,
+        })
+        .collect();
+    Ok(Texture::new(width, height, pixels))
+}
+
+/*
+ * Write the texture as an 8 bits RGB PNG file
+ */
+pub fn write_png(path: &Path, texture: &Texture) -> Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), texture.width as u32, texture.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let data: Vec<u8> = texture
+        .pixels
+        .iter()
+        .flat_map(|pixel| [pixel.x, pixel.y, pixel.z])
+        .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect();
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(writer.finish()?)
+}
+
+struct NetpbmReader {
+    data: Vec<u8>,
+    cursor: usize,
+}
+
+impl NetpbmReader {
+    fn next_token(&mut self) -> Option<&[u8]> {
+        loop {
+            while self.cursor < self.data.len() && self.data[self.cursor].is_ascii_whitespace() {
+                self.cursor += 1;
+            }
+            if self.cursor < self.data.len() && self.data[self.cursor] == b'#' {
+                while self.cursor < self.data.len() && self.data[self.cursor] != b'\n' {
+                    self.cursor += 1;
+                }
+                continue;
+            }
+            break;
+        }
+        let start = self.cursor;
+        while self.cursor < self.data.len() && !self.data[self.cursor].is_ascii_whitespace() {
+            self.cursor += 1;
+        }
+        (start < self.cursor).then(|| &self.data[start..self.cursor])
+    }
+
+    fn next_number(&mut self) -> Result<usize> {
+        self.next_token()
+            .and_then(|token| std::str::from_utf8(token).ok())
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| invalid_data("Invalid PPM/PGM content"))
+    }
+}
+
+/*
+ * P2/P5 (grey) and P3/P6 (colour), the header may contain '#' comments
+ */
+fn read_netpbm(path: &Path) -> Result<Texture> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    let mut reader = NetpbmReader { data, cursor: 0 };
+
+    let (channels, binary) = match reader.next_token() {
+        Some(b"P2") => (1, false),
+        Some(b"P3") => (3, false),
+        Some(b"P5") => (1, true),
+        Some(b"P6") => (3, true),
+        _ => return Err(invalid_data("Unknown PPM/PGM magic number")),
+    };
+    let width = reader.next_number()?;
+    let height = reader.next_number()?;
+    let max_value = reader.next_number()?;
+    if width == 0 || height == 0 || max_value == 0 || max_value > 65535 {
+        return Err(invalid_data("Invalid PPM/PGM size"));
+    }
+
+    // the size comes from the file, it may not fit in memory
+    let count = width.checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or_else(|| invalid_data("Invalid PPM/PGM size"))?;
+    let samples: Vec<usize> = if binary {
+        // a single whitespace separates the header from the raster
+        let start = reader.cursor + 1;
+        let sample_size = if max_value < 256 { 1 } else { 2 };
+        let end = count.checked_mul(sample_size)
+            .and_then(|length| length.checked_add(start))
+            .ok_or_else(|| invalid_data("Invalid PPM/PGM size"))?;
+        let raster = reader.data.get(start..end)
+            .ok_or_else(|| invalid_data("Truncated PPM/PGM raster"))?;
+        raster.chunks_exact(sample_size)
+            .map(|sample| sample.iter().fold(0, |acc, &byte| (acc << 8) | byte as usize))
+            .collect()
+    } else {
+        (0..count).map(|_| reader.next_number()).collect::<Result<_>>()?
+    };
+
+    let max_value = max_value as f32;
+    let pixels = samples.chunks_exact(channels)
+        .map(|pixel| match channels {
+            1 => Vec3::new(pixel[0] as f32, pixel[0] as f32, pixel[0] as f32) / max_value,
+            _ => Vec3::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32) / max_value,
+        })
+        .collect();
+    Ok(Texture::new(width, height, pixels))
+}
@@ -0,0 +1,66 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::bindings::{Action, Key, KeyBindings, Layout};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Several(Vec<String>),
+}
+
+/*
+ * The layout gives the default keys, each action listed in `bindings` gets its keys replaced
+ */
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyBindingsDescription {
+    layout: Option<String>,
+    #[serde(default)]
+    bindings: BTreeMap<String, KeyList>,
+}
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/*
+ * Read the key bindings of a TOML file, `layout` is used when the file does not give one
+ */
+pub fn read_key_bindings_file(path: &Path, layout: Layout) -> Result<KeyBindings> {
+    let content = fs::read_to_string(path)?;
+    let description: KeyBindingsDescription = toml::from_str(&content)
+        .map_err(|e| invalid_data(format!("Invalid key bindings file {:?}: {}", path, e)))?;
+    let layout = match &description.layout {
+        Some(layout) => layout.parse::<Layout>().map_err(invalid_data)?,
+        None => layout,
+    };
+    let mut key_bindings = KeyBindings::profile(layout);
+    rebind(&mut key_bindings, &description.bindings)?;
+    Ok(key_bindings)
+}
+
+/*
+ * Replace the keys of each listed action
+ */
+pub fn rebind(key_bindings: &mut KeyBindings, bindings: &BTreeMap<String, KeyList>) -> Result<()> {
+    for (action, keys) in bindings {
+        let action = action.parse::<Action>().map_err(invalid_data)?;
+        let keys = match keys {
+            KeyList::One(key) => vec![key.clone()],
+            KeyList::Several(keys) => keys.clone(),
+        };
+        key_bindings.unbind(action);
+        for key in keys {
+            key_bindings.bind(key.parse::<Key>().map_err(invalid_data)?, action);
+        }
+    }
+    Ok(())
+}
@@ -1,64 +1,375 @@
+use std::fmt;
+use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
-use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+pub const DEFAULT_LOG_FILE: &str = "engine_3D.log";
+// size of the log file before it is rotated
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+// rotated files kept: engine_3D.log.1, engine_3D.log.2...
+pub const DEFAULT_KEEP: usize = 3;
 
-#[derive(Default, Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+  Error,
+  Warn,
+  Info,
+  Debug,
+  Trace
+}
+
+impl FromStr for Level {
+  type Err = String;
+
+  fn from_str (value: &str) -> Result<Self, Self::Err> {
+    match value.to_lowercase().as_str() {
+      "error" => Ok(Level::Error),
+      "warn" => Ok(Level::Warn),
+      "info" => Ok(Level::Info),
+      "debug" => Ok(Level::Debug),
+      "trace" => Ok(Level::Trace),
+      _ => Err(format!("Unknown log level '{}' (off, error, warn, info, debug, trace)", value))
+    }
+  }
+}
+
+impl fmt::Display for Level {
+  fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      Level::Error => "error",
+      Level::Warn => "warn",
+      Level::Info => "info",
+      Level::Debug => "debug",
+      Level::Trace => "trace"
+    };
+    f.pad(name)
+  }
+}
+
+/*
+ * Most verbose level written, None writes nothing ("off")
+ */
+fn parse_level_filter (value: &str) -> Result<Option<Level>, String> {
+  match value.trim() {
+    "off" => Ok(None),
+    level => level.parse::<Level>().map(Some)
+  }
+}
+
+fn level_filter_name (level: Option<Level>) -> String {
+  level.map_or("off".to_string(), |level| level.to_string())
+}
+
+/*
+ * Message with where it comes from, formatted only by the sink
+ */
+pub struct Record<'a> {
+  pub level: Level,
+  pub module: &'a str,
+  pub args: fmt::Arguments<'a>
+}
+
+/*
+ * Destination of the messages, a library user can give its own to the logger
+ */
+pub trait LogSink: Send + Sync {
+  fn write (&self, record: &Record);
+
+  fn flush (&self) {}
+}
+
+/*
+ * Lines appended to a file created on the first message. Beyond `max_size` bytes the file
+ * becomes `<path>.1`, the previous `<path>.1` becomes `<path>.2`... up to `keep` files.
+ */
+pub struct FileSink {
+  path: PathBuf,
+  max_size: u64,
+  keep: usize,
+  // opened file and its size
+  file: Mutex<Option<(File, u64)>>
+}
+
+impl FileSink {
+  pub fn new (path: &Path, max_size: u64, keep: usize) -> FileSink {
+    FileSink { path: path.to_path_buf(), max_size, keep, file: Mutex::new(None) }
+  }
+
+  pub fn path (&self) -> &Path {
+    &self.path
+  }
+
+  fn rotated_path (&self, index: usize) -> PathBuf {
+    let mut name = self.path.clone().into_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+  }
+
+  fn rotate (&self) {
+    if self.keep == 0 {
+      let _ = fs::remove_file(&self.path);
+      return;
+    }
+    for index in (1..self.keep).rev() {
+      let _ = fs::rename(self.rotated_path(index), self.rotated_path(index + 1));
+    }
+    let _ = fs::rename(&self.path, self.rotated_path(1));
+  }
+
+  fn open (&self) -> Option<(File, u64)> {
+    let file = File::options().create(true).append(true).open(&self.path).ok()?;
+    let size = file.metadata().map_or(0, |metadata| metadata.len());
+    Some((file, size))
+  }
+}
+
+impl LogSink for FileSink {
+  fn write (&self, record: &Record) {
+    let line = format!("{} {:<5} {}: {}\n", timestamp(), record.level, record.module, record.args);
+    let Ok(mut file) = self.file.lock() else {
+      return;
+    };
+    if file.is_none() {
+      *file = self.open();
+    }
+    if self.max_size > 0 && file.as_ref().is_some_and(|(_, size)| size + line.len() as u64 > self.max_size && *size > 0) {
+      *file = None;
+      self.rotate();
+      *file = self.open();
+    }
+    if let Some((file, size)) = file.as_mut() {
+      if file.write_all(line.as_bytes()).is_ok() {
+        *size += line.len() as u64;
+      }
+    }
+  }
+
+  fn flush (&self) {
+    if let Ok(mut file) = self.file.lock() {
+      if let Some((file, _)) = file.as_mut() {
+        let _ = file.flush();
+      }
+    }
+  }
+}
+
+/*
+ * Seconds since the Unix epoch, with the milliseconds
+ */
+fn timestamp () -> String {
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+  format!("{}.{:03}", now.as_secs(), now.subsec_millis())
+}
+
+/*
+ * Messages filtered by level, for the whole program and per module, before reaching the sink.
+ * The messages are formatted only when they pass the filters, see the macros `log_error!`... `log_trace!`.
+ */
+#[derive(Clone)]
 pub struct Logger {
-  pub log_file_path: String,
-  pub log_file: Option<File>,
-  pub enable: bool
+  pub level: Option<Level>,
+  // level of the modules under a path ("tools::wavefront", "engine_3d::core"), the longest path wins
+  filters: Vec<(String, Option<Level>)>,
+  sink: Arc<dyn LogSink>,
+  // file of the default sink, shown to the user
+  pub log_file_path: String
+}
+
+impl Default for Logger {
+  fn default () -> Self {
+    Logger::new()
+  }
 }
 
+impl fmt::Debug for Logger {
+  fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Logger")
+      .field("level", &self.level)
+      .field("filters", &self.filters)
+      .field("log_file_path", &self.log_file_path)
+      .finish()
+  }
+}
 
 impl Logger {
+  /*
+   * Warnings and errors in engine_3D.log, created when the first one comes
+   */
   pub fn new () -> Logger {
-    let log_file_path: &str = "engine_3D.log";
-    let log_file: File = File::options().append(true).open(Path::new(log_file_path))
-      .expect("Error to open log file");
-
-    Logger {
-      log_file_path: String::from(log_file_path),
-      log_file: Some(log_file),
-      enable: false
-    }
+    Logger::from(Path::new(DEFAULT_LOG_FILE))
   }
 
   pub fn from (log_file_path: &Path) -> Logger {
-    let log_file: File = File::options().append(true).open(log_file_path)
-      .expect("Error to open log file");
+    Logger::to_file(log_file_path, DEFAULT_MAX_SIZE, DEFAULT_KEEP)
+  }
 
-    Logger {
-      log_file_path: String::from(log_file_path.to_str().unwrap()),
-      log_file: Some(log_file),
-      enable: false
-    }
+  /*
+   * Rotated beyond `max_size` bytes (0 never), `keep` previous files kept
+   */
+  pub fn to_file (log_file_path: &Path, max_size: u64, keep: usize) -> Logger {
+    let sink = FileSink::new(log_file_path, max_size, keep);
+    let mut logger = Logger::with_sink(Arc::new(sink));
+    logger.log_file_path = log_file_path.to_string_lossy().to_string();
+    logger
+  }
+
+  pub fn with_sink (sink: Arc<dyn LogSink>) -> Logger {
+    Logger { level: Some(Level::Warn), filters: vec![], sink, log_file_path: String::new() }
   }
 
+  /*
+   * Debug messages of every module
+   */
   pub fn enable_log (&mut self) {
-    self.enable = true
+    self.level = Some(self.level.map_or(Level::Debug, |level| level.max(Level::Debug)));
   }
 
   pub fn disable_log (&mut self) {
-    self.enable = false
-  }
-
-  pub fn log (&self, msg: String) {
-    if self.enable {
-      if self.log_file.is_some() {
-        let mut file: &File = self.log_file
-          .as_ref()
-          .expect("Error to retrieve the file");
-        match writeln!(file, "{}", msg) {
-          _ => ()
-        }
+    self.level = None;
+    self.filters.clear();
+  }
+
+  pub fn is_enabled (&self) -> bool {
+    self.level.is_some() || self.filters.iter().any(|(_, level)| level.is_some())
+  }
+
+  /*
+   * Level of the modules under `path`
+   */
+  pub fn filter (&mut self, path: &str, level: Option<Level>) {
+    self.filters.retain(|(filter, _)| filter != path);
+    self.filters.push((path.to_string(), level));
+  }
+
+  /*
+   * "debug", "warn,tools::wavefront=trace", "off,core::engine=debug"...
+   */
+  pub fn set_spec (&mut self, spec: &str) -> Result<(), String> {
+    for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+      match part.split_once('=') {
+        Some((path, level)) => self.filter(path.trim(), parse_level_filter(level)?),
+        None => self.level = parse_level_filter(part)?
       }
     }
+    Ok(())
   }
+
+  pub fn spec (&self) -> String {
+    std::iter::once(level_filter_name(self.level))
+      .chain(self.filters.iter().map(|(path, level)| format!("{}={}", path, level_filter_name(*level))))
+      .collect::<Vec<String>>()
+      .join(",")
+  }
+
+  fn level_of (&self, module: &str) -> Option<Level> {
+    // the paths can be given without the crate name
+    let relative = module.strip_prefix(concat!(env!("CARGO_CRATE_NAME"), "::")).unwrap_or(module);
+    let is_under = |path: &str| [module, relative].iter().any(|module| {
+      module.strip_prefix(path).is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    });
+    self.filters.iter()
+      .filter(|(path, _)| is_under(path))
+      .max_by_key(|(path, _)| path.len())
+      .map_or(self.level, |(_, level)| *level)
+  }
+
+  pub fn enabled (&self, level: Level, module: &str) -> bool {
+    self.level_of(module).is_some_and(|max| level <= max)
+  }
+
+  /*
+   * Send the message to the sink without filtering, the macros check `enabled` first
+   */
+  pub fn write (&self, level: Level, module: &str, args: fmt::Arguments) {
+    self.sink.write(&Record { level, module, args });
+  }
+
+  pub fn flush (&self) {
+    self.sink.flush();
+  }
+}
+
+impl std::fmt::Display for Logger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Logger: [path={}, level={}]", self.log_file_path, self.spec())
+    }
 }
 
-impl std::string::ToString for Logger {
-    fn to_string(&self) -> String {
-        format!("Logger: [path={}]", self.log_file_path)
+#[cfg(feature = "log")]
+impl From<log::Level> for Level {
+  fn from (level: log::Level) -> Level {
+    match level {
+      log::Level::Error => Level::Error,
+      log::Level::Warn => Level::Warn,
+      log::Level::Info => Level::Info,
+      log::Level::Debug => Level::Debug,
+      log::Level::Trace => Level::Trace
     }
-}
\ No newline at end of file
+  }
+}
+
+#[cfg(feature = "log")]
+impl log::Log for Logger {
+  fn enabled (&self, metadata: &log::Metadata) -> bool {
+    Logger::enabled(self, metadata.level().into(), metadata.target())
+  }
+
+  fn log (&self, record: &log::Record) {
+    let module = record.module_path().unwrap_or(record.target());
+    if Logger::enabled(self, record.level().into(), module) {
+      self.write(record.level().into(), module, *record.args());
+    }
+  }
+
+  fn flush (&self) {
+    Logger::flush(self);
+  }
+}
+
+/*
+ * Messages of the `log` facade (dependencies, library users) go to a copy of the logger
+ */
+#[cfg(feature = "log")]
+pub fn install_log_facade (logger: &Logger) -> Result<(), log::SetLoggerError> {
+  log::set_boxed_logger(Box::new(logger.clone()))?;
+  log::set_max_level(log::LevelFilter::Trace);
+  Ok(())
+}
+
+#[macro_export]
+macro_rules! log_at {
+  ($logger:expr, $level:expr, $($arg:tt)+) => {{
+    let logger: &$crate::tools::logger::Logger = &$logger;
+    if logger.enabled($level, module_path!()) {
+      logger.write($level, module_path!(), format_args!($($arg)+));
+    }
+  }};
+}
+
+#[macro_export]
+macro_rules! log_error {
+  ($logger:expr, $($arg:tt)+) => { $crate::log_at!($logger, $crate::tools::logger::Level::Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+  ($logger:expr, $($arg:tt)+) => { $crate::log_at!($logger, $crate::tools::logger::Level::Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_info {
+  ($logger:expr, $($arg:tt)+) => { $crate::log_at!($logger, $crate::tools::logger::Level::Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+  ($logger:expr, $($arg:tt)+) => { $crate::log_at!($logger, $crate::tools::logger::Level::Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_trace {
+  ($logger:expr, $($arg:tt)+) => { $crate::log_at!($logger, $crate::tools::logger::Level::Trace, $($arg)+) };
+}
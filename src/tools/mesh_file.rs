@@ -0,0 +1,176 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use crate::core::{mesh::Mesh, scene::Scene};
+
+use super::{gltf, logger::Logger, ply, stl, stl::StlEncoding, wavefront};
+
+/*
+ * Error of the mesh loaders, converted into an `io::Error` where the callers expect one
+ */
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    // malformed content, with its line for the text formats
+    Parse {
+        path: PathBuf,
+        line: Option<usize>,
+        message: String,
+    },
+    UnknownFormat(PathBuf),
+}
+
+impl LoadError {
+    pub fn parse(path: &Path, line: Option<usize>, message: impl Into<String>) -> LoadError {
+        LoadError::Parse {
+            path: path.to_path_buf(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{}", e),
+            LoadError::Parse {
+                path,
+                line: Some(line),
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            LoadError::Parse { path, line: None, message } => write!(f, "{}: {}", path.display(), message),
+            LoadError::UnknownFormat(path) => write!(
+                f,
+                "Unknown mesh format for {:?} (expected {})",
+                path,
+                MeshFormat::EXTENSIONS.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+impl From<LoadError> for io::Error {
+    fn from(e: LoadError) -> io::Error {
+        match e {
+            LoadError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MeshFormat {
+    Wavefront,
+    Stl,
+    Ply,
+    Gltf,
+}
+
+impl MeshFormat {
+    pub const EXTENSIONS: [&'static str; 5] = [".obj", ".stl", ".ply", ".gltf", ".glb"];
+
+    /*
+     * Format given by the extension of the file, whatever its case
+     */
+    pub fn from_path(path: &Path) -> Option<MeshFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "obj" => Some(MeshFormat::Wavefront),
+            "stl" => Some(MeshFormat::Stl),
+            "ply" => Some(MeshFormat::Ply),
+            "gltf" | "glb" => Some(MeshFormat::Gltf),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * Read the mesh with the loader of its format, a glTF scene baked into a single mesh
+ */
+pub fn read_mesh_file(path: &Path, logger: &Logger) -> Result<Mesh, LoadError> {
+    match MeshFormat::from_path(path) {
+        Some(MeshFormat::Wavefront) => wavefront::read_object_file(path, logger),
+        Some(MeshFormat::Stl) => stl::read_stl_file(path, logger),
+        Some(MeshFormat::Ply) => ply::read_ply_file(path, logger),
+        Some(MeshFormat::Gltf) => Ok(gltf::read_gltf_file(path, logger)?.baked_mesh()),
+        None => Err(LoadError::UnknownFormat(path.to_path_buf())),
+    }
+}
+
+/*
+ * Read the file as a scene: the glTF one with its hierarchy, camera and lights,
+ * a single node displaying the mesh for the other formats
+ */
+pub fn read_mesh_scene(path: &Path, logger: &Logger) -> Result<Scene, LoadError> {
+    match MeshFormat::from_path(path) {
+        Some(MeshFormat::Gltf) => gltf::read_gltf_file(path, logger),
+        _ => Ok(Scene::from_mesh(&path.display().to_string(), read_mesh_file(path, logger)?)),
+    }
+}
+
+/*
+ * Write the mesh in the format of the file, STL with the given encoding, OBJ with its MTL materials
+ */
+pub fn write_mesh_file(path: &Path, mesh: &Mesh, encoding: StlEncoding) -> Result<(), LoadError> {
+    match MeshFormat::from_path(path) {
+        Some(MeshFormat::Stl) => Ok(stl::write_stl_file(path, mesh, encoding)?),
+        Some(MeshFormat::Wavefront) => {
+            let name = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+            Ok(wavefront::write_object_file(path, &[(&name, mesh)])?)
+        }
+        _ => Err(LoadError::UnknownFormat(path.to_path_buf())),
+    }
+}
+
+/*
+ * Write the scene, an OBJ object per node placed in the world when `baked`, else per mesh
+ * in its own coordinates. STL has no objects, the scene is always baked into a single mesh
+ * written with `encoding`. Returns the number of triangles written.
+ */
+pub fn write_scene_file(path: &Path, scene: &Scene, baked: bool, encoding: StlEncoding) -> Result<usize, LoadError> {
+    match MeshFormat::from_path(path) {
+        Some(MeshFormat::Wavefront) if baked => {
+            let nodes = scene.baked_nodes();
+            let objects: Vec<(&str, &Mesh)> = nodes.iter().map(|(node, mesh)| (node.name.as_str(), mesh)).collect();
+            wavefront::write_object_file(path, &objects)?;
+            Ok(nodes.iter().map(|(_, mesh)| mesh.triangles.len()).sum())
+        }
+        Some(MeshFormat::Wavefront) => {
+            // each mesh named after the first node drawing it
+            let objects: Vec<(&str, &Mesh)> = scene
+                .meshes()
+                .iter()
+                .enumerate()
+                .map(|(index, mesh)| {
+                    let node = scene.nodes.iter().find(|node| node.mesh == Some(index));
+                    (node.map_or("mesh", |node| node.name.as_str()), mesh)
+                })
+                .collect();
+            wavefront::write_object_file(path, &objects)?;
+            Ok(scene.meshes().iter().map(|mesh| mesh.triangles.len()).sum())
+        }
+        _ => {
+            let mesh = scene.baked_mesh();
+            write_mesh_file(path, &mesh, encoding)?;
+            Ok(mesh.triangles.len())
+        }
+    }
+}
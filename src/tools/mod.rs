@@ -1,3 +1,15 @@
 pub mod configuration;
 pub mod wavefront;
-pub mod logger;
\ No newline at end of file
+pub mod stl;
+pub mod ply;
+pub mod gltf;
+pub mod mesh_file;
+pub mod logger;
+pub mod image;
+pub mod benchmark;
+pub mod scene_file;
+pub mod camera_path_file;
+pub mod key_bindings_file;
+pub mod config_file;
+#[cfg(test)]
+mod test_files;
\ No newline at end of file
@@ -0,0 +1,632 @@
+use std::{fs, path::Path, str::SplitAsciiWhitespace, sync::Arc};
+
+use crate::core::{
+    material::Material,
+    math::{
+        math::{cross_prod, dot},
+        triangle::Triangle3D,
+        vector::{Vec2, Vec3},
+    },
+    mesh::{Mesh, Point},
+};
+
+use super::{image::read_image_file, logger::Logger, mesh_file::LoadError};
+use crate::{log_debug, log_warn};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => Some(ScalarType::Int8),
+            "uchar" | "uint8" => Some(ScalarType::UInt8),
+            "short" | "int16" => Some(ScalarType::Int16),
+            "ushort" | "uint16" => Some(ScalarType::UInt16),
+            "int" | "int32" => Some(ScalarType::Int32),
+            "uint" | "uint32" => Some(ScalarType::UInt32),
+            "float" | "float32" => Some(ScalarType::Float32),
+            "double" | "float64" => Some(ScalarType::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    /*
+     * Value of a full channel: the maximum of the integer types, 1 for the floats
+     */
+    fn channel_max(self) -> f32 {
+        match self {
+            ScalarType::Int8 => i8::MAX as f32,
+            ScalarType::UInt8 => u8::MAX as f32,
+            ScalarType::Int16 => i16::MAX as f32,
+            ScalarType::UInt16 => u16::MAX as f32,
+            ScalarType::Int32 => i32::MAX as f32,
+            ScalarType::UInt32 => u32::MAX as f32,
+            ScalarType::Float32 | ScalarType::Float64 => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PropertyType {
+    Scalar(ScalarType),
+    // number of items, then the items
+    List { count: ScalarType, item: ScalarType },
+}
+
+impl PropertyType {
+    fn value_type(self) -> ScalarType {
+        match self {
+            PropertyType::Scalar(value) => value,
+            PropertyType::List { item, .. } => item,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlyProperty {
+    pub name: String,
+    pub kind: PropertyType,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlyElement {
+    pub name: String,
+    pub count: usize,
+    pub properties: Vec<PlyProperty>,
+}
+
+impl PlyElement {
+    fn property(&self, names: &[&str]) -> Option<usize> {
+        names
+            .iter()
+            .find_map(|name| self.properties.iter().position(|property| property.name == *name))
+    }
+
+    fn scalar(&self, names: &[&str]) -> Option<usize> {
+        self.property(names)
+            .filter(|&index| matches!(self.properties[index].kind, PropertyType::Scalar(_)))
+    }
+
+    fn list(&self, names: &[&str]) -> Option<usize> {
+        self.property(names)
+            .filter(|&index| matches!(self.properties[index].kind, PropertyType::List { .. }))
+    }
+
+    /*
+     * Bytes of a binary row whose lists are empty
+     */
+    fn min_row_size(&self) -> usize {
+        self.properties
+            .iter()
+            .map(|property| match property.kind {
+                PropertyType::Scalar(kind) => kind.size(),
+                PropertyType::List { count, .. } => count.size(),
+            })
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlyHeader {
+    pub format: PlyFormat,
+    pub elements: Vec<PlyElement>,
+    pub comments: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+impl Value {
+    fn scalar(&self) -> f64 {
+        match self {
+            Value::Scalar(value) => *value,
+            Value::List(values) => values.first().copied().unwrap_or_default(),
+        }
+    }
+
+    fn list(&self) -> &[f64] {
+        match self {
+            Value::Scalar(value) => std::slice::from_ref(value),
+            Value::List(values) => values,
+        }
+    }
+}
+
+/*
+ * Header lines up to "end_header", and the offset of the data after it
+ */
+pub fn parse_header(path: &Path, data: &[u8]) -> Result<(PlyHeader, usize), LoadError> {
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    let mut comments = Vec::new();
+    let mut offset = 0;
+    let mut number = 0;
+    loop {
+        let Some(length) = data[offset..].iter().position(|&byte| byte == b'\n') else {
+            return Err(LoadError::parse(path, None, "Missing 'end_header'"));
+        };
+        let line = String::from_utf8_lossy(&data[offset..offset + length]).trim().to_string();
+        offset += length + 1;
+        number += 1;
+        let error = |message: &str| LoadError::parse(path, Some(number), format!("{} in '{}'", message, line));
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens[..] {
+            ["ply"] if number == 1 => {}
+            _ if number == 1 => return Err(error("Not a PLY file, expected 'ply'")),
+            ["format", name, _version] => {
+                format = Some(match name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(error("Unknown format")),
+                });
+            }
+            ["comment", ..] | ["obj_info", ..] => comments.push(tokens[1..].join(" ")),
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| error("Invalid element count"))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => {
+                let kind = match (ScalarType::parse(count), ScalarType::parse(item)) {
+                    (Some(count), Some(item)) => PropertyType::List { count, item },
+                    _ => return Err(error("Unknown property type")),
+                };
+                let element = elements.last_mut().ok_or_else(|| error("Property before any element"))?;
+                element.properties.push(PlyProperty { name: name.to_string(), kind });
+            }
+            ["property", kind, name] => {
+                let kind = ScalarType::parse(kind).ok_or_else(|| error("Unknown property type"))?;
+                let element = elements.last_mut().ok_or_else(|| error("Property before any element"))?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    kind: PropertyType::Scalar(kind),
+                });
+            }
+            ["end_header"] => break,
+            [] => {}
+            _ => return Err(error("Unknown header line")),
+        }
+    }
+    let format = format.ok_or_else(|| LoadError::parse(path, None, "Missing 'format' in the header"))?;
+    Ok((PlyHeader { format, elements, comments }, offset))
+}
+
+/*
+ * Values after the header, whitespace separated or packed
+ */
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { data: &'a [u8], offset: usize, big_endian: bool },
+}
+
+impl Body<'_> {
+    /*
+     * Bytes left in a binary body, None for the text
+     */
+    fn remaining(&self) -> Option<usize> {
+        match self {
+            Body::Ascii(_) => None,
+            Body::Binary { data, offset, .. } => Some(data.len().saturating_sub(*offset)),
+        }
+    }
+
+    fn read(&mut self, kind: ScalarType) -> Result<f64, String> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens.next().ok_or("Unexpected end of the data")?;
+                token.parse().map_err(|_| format!("Invalid number '{}'", token))
+            }
+            Body::Binary { data, offset, big_endian } => {
+                let size = kind.size();
+                let bytes = data.get(*offset..*offset + size).ok_or("Unexpected end of the data")?;
+                *offset += size;
+                let mut buffer = [0u8; 8];
+                buffer[..size].copy_from_slice(bytes);
+                if *big_endian {
+                    buffer[..size].reverse();
+                }
+                let [b0, b1, b2, b3, ..] = buffer;
+                Ok(match kind {
+                    ScalarType::Int8 => b0 as i8 as f64,
+                    ScalarType::UInt8 => b0 as f64,
+                    ScalarType::Int16 => i16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::UInt16 => u16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::Int32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::UInt32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::Float32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::Float64 => f64::from_le_bytes(buffer),
+                })
+            }
+        }
+    }
+
+    fn read_row(&mut self, element: &PlyElement, row: &mut Vec<Value>) -> Result<(), String> {
+        row.clear();
+        for property in &element.properties {
+            row.push(match property.kind {
+                PropertyType::Scalar(kind) => Value::Scalar(self.read(kind)?),
+                PropertyType::List { count, item } => {
+                    let count = self.read(count)?;
+                    if count < 0.0 {
+                        return Err(format!("Negative length of the list '{}'", property.name));
+                    }
+                    Value::List((0..count as usize).map(|_| self.read(item)).collect::<Result<_, _>>()?)
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Vertex {
+    position: Vec3,
+    normal: Option<Vec3>,
+    color: Option<Vec3>,
+    uv: Option<Vec2>,
+}
+
+/*
+ * Properties of a vertex element read by the engine, the others are ignored
+ */
+struct VertexLayout {
+    position: [usize; 3],
+    normal: Option<[usize; 3]>,
+    // with the value of a full channel
+    color: Option<([usize; 3], [f32; 3])>,
+    uv: Option<[usize; 2]>,
+}
+
+impl VertexLayout {
+    fn new(element: &PlyElement) -> Option<VertexLayout> {
+        let scalars = |names: [&[&str]; 3]| -> Option<[usize; 3]> {
+            Some([element.scalar(names[0])?, element.scalar(names[1])?, element.scalar(names[2])?])
+        };
+        let uv = match (
+            element.scalar(&["u", "s", "texture_u", "texture_s"]),
+            element.scalar(&["v", "t", "texture_v", "texture_t"]),
+        ) {
+            (Some(u), Some(v)) => Some([u, v]),
+            _ => None,
+        };
+        Some(VertexLayout {
+            position: scalars([&["x"], &["y"], &["z"]])?,
+            normal: scalars([&["nx"], &["ny"], &["nz"]]),
+            color: color_layout(element),
+            uv,
+        })
+    }
+
+    fn vertex(&self, row: &[Value]) -> Vertex {
+        let vec3 = |[x, y, z]: [usize; 3]| Vec3::new(row[x].scalar() as f32, row[y].scalar() as f32, row[z].scalar() as f32);
+        Vertex {
+            position: vec3(self.position),
+            normal: self.normal.map(vec3),
+            color: self.color.map(|color| read_color(row, color)),
+            uv: self.uv.map(|[u, v]| Vec2::new(row[u].scalar() as f32, row[v].scalar() as f32)),
+        }
+    }
+}
+
+/*
+ * Properties of the red, green and blue channels, with the value of a full channel
+ */
+fn color_layout(element: &PlyElement) -> Option<([usize; 3], [f32; 3])> {
+    let indices = [
+        element.scalar(&["red", "r", "diffuse_red"])?,
+        element.scalar(&["green", "g", "diffuse_green"])?,
+        element.scalar(&["blue", "b", "diffuse_blue"])?,
+    ];
+    Some((indices, indices.map(|index| element.properties[index].kind.value_type().channel_max())))
+}
+
+fn read_color(row: &[Value], (indices, max): ([usize; 3], [f32; 3])) -> Vec3 {
+    let channel = |i: usize| row[indices[i]].scalar() as f32 / max[i];
+    Vec3::new(channel(0), channel(1), channel(2))
+}
+
+#[derive(Debug, Clone)]
+struct Face {
+    // none when an index is negative or not an integer
+    indices: Option<Vec<usize>>,
+    // texture coordinates of the corners, as written by MeshLab
+    uv: Option<Vec<Vec2>>,
+    color: Option<Vec3>,
+}
+
+/*
+ * Read an ASCII or binary PLY file. The vertices keep their normal, colour and texture coordinates,
+ * the faces are split in fans. Without faces, the vertices make a point cloud.
+ * A texture named by a "comment TextureFile" line is applied to the faces.
+ */
+pub fn read_ply_file(path: &Path, logger: &Logger) -> Result<Mesh, LoadError> {
+    let data = fs::read(path)?;
+    let (header, offset) = parse_header(path, &data)?;
+    let mut body = match header.format {
+        PlyFormat::Ascii => Body::Ascii(
+            std::str::from_utf8(&data[offset..])
+                .map_err(|_| LoadError::parse(path, None, "Invalid text in an ASCII PLY file"))?
+                .split_ascii_whitespace(),
+        ),
+        format => Body::Binary {
+            data: &data[offset..],
+            offset: 0,
+            big_endian: format == PlyFormat::BinaryBigEndian,
+        },
+    };
+
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut faces: Vec<Face> = Vec::new();
+    let mut row: Vec<Value> = Vec::new();
+    for element in &header.elements {
+        let error = |index: usize, message: String| {
+            LoadError::parse(path, None, format!("{} {}: {}", element.name, index, message))
+        };
+        let vertex_layout = match element.name.as_str() {
+            "vertex" => Some(VertexLayout::new(element).ok_or_else(|| {
+                LoadError::parse(path, None, "The vertex element needs the properties x, y and z")
+            })?),
+            _ => None,
+        };
+        let indices = element.list(&["vertex_indices", "vertex_index"]);
+        let texcoord = element.list(&["texcoord"]);
+        let face_color = color_layout(element);
+        if vertex_layout.is_none() && element.name != "face" {
+            log_debug!(logger, "{:?}: element '{}' skipped", path, element.name);
+        }
+        // a row without properties reads nothing, a count too large for the data would not end
+        if element.count > 0 && element.properties.is_empty() {
+            let message = format!("The element '{}' has {} rows but no properties", element.name, element.count);
+            return Err(LoadError::parse(path, None, message));
+        }
+        if let Some(remaining) = body.remaining() {
+            if element.count.saturating_mul(element.min_row_size()) > remaining {
+                let message = format!(
+                    "{} rows of the element '{}' cannot fit in the {} bytes left",
+                    element.count, element.name, remaining
+                );
+                return Err(LoadError::parse(path, None, message));
+            }
+        }
+        for index in 0..element.count {
+            body.read_row(element, &mut row).map_err(|message| error(index, message))?;
+            if let Some(layout) = &vertex_layout {
+                vertices.push(layout.vertex(&row));
+            } else if element.name == "face" {
+                let Some(indices) = indices else {
+                    return Err(error(index, "The face element needs the list vertex_indices".to_string()));
+                };
+                let uv = texcoord.map(|texcoord| {
+                    row[texcoord]
+                        .list()
+                        .chunks_exact(2)
+                        .map(|uv| Vec2::new(uv[0] as f32, uv[1] as f32))
+                        .collect()
+                });
+                let indices = row[indices]
+                    .list()
+                    .iter()
+                    .map(|&index| (index >= 0.0 && index.fract() == 0.0).then_some(index as usize))
+                    .collect();
+                faces.push(Face {
+                    indices,
+                    uv,
+                    color: face_color.map(|color| read_color(&row, color)),
+                });
+            }
+        }
+    }
+
+    if faces.is_empty() {
+        log_debug!(logger, "{:?}: point cloud of {} vertices", path, vertices.len());
+        let points = vertices
+            .iter()
+            .map(|vertex| Point {
+                position: vertex.position,
+                normal: vertex.normal,
+                color: vertex.color,
+            })
+            .collect();
+        return Ok(Mesh::from_points(points));
+    }
+
+    let mut mesh = Mesh::default();
+    let texture_file = header
+        .comments
+        .iter()
+        .find_map(|comment| comment.strip_prefix("TextureFile "))
+        .map(|name| path.with_file_name(name.trim()));
+    if let Some(texture_path) = texture_file {
+        match read_image_file(&texture_path) {
+            Ok(texture) => {
+                let mut material = Material::new(&texture_path.to_string_lossy());
+                material.texture = Some(Arc::new(texture));
+                mesh.materials.push(material);
+            }
+            Err(e) => log_warn!(logger, "Unable to read the texture {:?}: {}", texture_path, e),
+        }
+    }
+    let material = (!mesh.materials.is_empty()).then_some(0);
+
+    let mut invalid = 0;
+    for face in &faces {
+        let Some(indices) = face
+            .indices
+            .as_ref()
+            .filter(|indices| indices.len() >= 3 && indices.iter().all(|&index| index < vertices.len()))
+        else {
+            invalid += 1;
+            continue;
+        };
+        let corner = |i: usize| {
+            let vertex = vertices[indices[i]];
+            let uv = face.uv.as_ref().and_then(|uv| uv.get(i).copied()).or(vertex.uv);
+            (vertex, uv, face.color.or(vertex.color))
+        };
+        for i in 1..indices.len() - 1 {
+            let mut corners = [corner(0), corner(i), corner(i + 1)];
+            let positions = corners.map(|(vertex, _, _)| vertex.position);
+            // the vertex normals give the front face
+            if let [Some(n1), Some(n2), Some(n3)] = corners.map(|(vertex, _, _)| vertex.normal) {
+                let winding = cross_prod(positions[1] - positions[0], positions[2] - positions[0]);
+                if dot(n1 + n2 + n3, winding) < 0.0 {
+                    corners.swap(1, 2);
+                }
+            }
+            let mut triangle = Triangle3D::new(corners[0].0.position, corners[1].0.position, corners[2].0.position)
+                .with_material(material);
+            if let [(_, Some(uv1), _), (_, Some(uv2), _), (_, Some(uv3), _)] = corners {
+                triangle = triangle.with_uv([uv1, uv2, uv3]);
+            }
+            if let [(_, _, Some(c1)), (_, _, Some(c2)), (_, _, Some(c3))] = corners {
+                triangle = triangle.with_colors([c1, c2, c3]);
+            }
+            mesh.triangles.push(triangle);
+        }
+    }
+    if invalid > 0 {
+        log_warn!(logger, "{:?}: {} faces with less than 3 vertices or invalid indices skipped", path, invalid);
+    }
+    log_debug!(
+        logger,
+        "{:?}: {} vertices, {} faces, {} triangles",
+        path,
+        vertices.len(),
+        faces.len(),
+        mesh.triangles.len()
+    );
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::test_files::{corners, read_file};
+
+    fn read(name: &str, data: &[u8]) -> Result<Mesh, LoadError> {
+        read_file(name, "test.ply", data, read_ply_file)
+    }
+
+    fn binary_square(format: &str, bytes: fn(f32) -> [u8; 4], index_bytes: fn(i32) -> [u8; 4]) -> Vec<u8> {
+        let mut data = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0] {
+            data.extend(bytes(value));
+        }
+        for face in [&[0i32, 1, 2][..], &[0, 2, 3], &[0, -1, 2], &[0, 1, 4]] {
+            data.push(face.len() as u8);
+            for &index in face {
+                data.extend(index_bytes(index));
+            }
+        }
+        data
+    }
+
+    const HEADER: &str = "element vertex 4\n\
+        property float x\nproperty float y\nproperty float z\n\
+        element face 4\nproperty list uchar int vertex_indices\nend_header\n";
+
+    #[test]
+    fn ascii_faces_with_invalid_indices_are_skipped() {
+        let data = format!(
+            "ply\nformat ascii 1.0\n{}0 0 0\n1 0 0\n1 1 0\n0 1 0\n4 0 1 2 3\n3 0 -1 2\n3 0 1.5 2\n3 0 1 4\n",
+            HEADER
+        );
+        let mesh = read("ply_ascii", data.as_bytes()).unwrap();
+        // the quad in a fan, the other faces skipped
+        assert_eq!(mesh.triangles.len(), 2);
+        assert_eq!(corners(&mesh.triangles[0]), [(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0)]);
+        assert_eq!(corners(&mesh.triangles[1]), [(0.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)]);
+    }
+
+    #[test]
+    fn binary_faces_with_invalid_indices_are_skipped() {
+        let little = binary_square("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes);
+        let big = binary_square("binary_big_endian", f32::to_be_bytes, i32::to_be_bytes);
+        for (name, data) in [("ply_binary", little), ("ply_binary_big", big)] {
+            let mesh = read(name, &data).unwrap();
+            assert_eq!(mesh.triangles.len(), 2);
+            assert_eq!(corners(&mesh.triangles[1]), [(0.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)]);
+        }
+    }
+
+    #[test]
+    fn counts_beyond_the_data_are_rejected() {
+        for format in ["ascii", "binary_little_endian"] {
+            let data = format!("ply\nformat {} 1.0\nelement extra 1000000000000\nend_header\n", format);
+            let e = read("ply_no_properties", data.as_bytes()).unwrap_err();
+            assert!(e.to_string().contains("no properties"), "{}", e);
+        }
+        let mut data = binary_square("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes);
+        // 4 vertices of 12 bytes announced, 3 given and no faces
+        let vertices_end = data.len() - 4 * (1 + 3 * 4);
+        data.truncate(vertices_end - 12);
+        let e = read("ply_short", &data).unwrap_err();
+        assert!(e.to_string().contains("cannot fit"), "{}", e);
+    }
+
+    #[test]
+    fn vertex_colors_reach_the_triangles() {
+        let data = "ply\nformat ascii 1.0\nelement vertex 3\n\
+            property float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n3 0 1 2\n";
+        let mesh = read("ply_colors", data.as_bytes()).unwrap();
+        let colors = mesh.triangles[0].colors.unwrap().map(|c| (c.x, c.y, c.z));
+        assert_eq!(colors, [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, 0.0, 1.0)]);
+    }
+
+    #[test]
+    fn vertices_without_faces_are_points() {
+        let data = "ply\nformat ascii 1.0\nelement vertex 2\n\
+            property float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\nend_header\n\
+            0 0 0 255 255 255\n1 2 3 0 0 255\n";
+        let mesh = read("ply_points", data.as_bytes()).unwrap();
+        assert!(mesh.triangles.is_empty());
+        let points: Vec<_> = mesh.points.iter().map(|point| point.position).map(|v| (v.x, v.y, v.z)).collect();
+        assert_eq!(points, [(0.0, 0.0, 0.0), (1.0, 2.0, 3.0)]);
+        assert_eq!(mesh.points[1].color.map(|c| (c.x, c.y, c.z)), Some((0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn header_is_parsed() {
+        let data = format!("ply\nformat ascii 1.0\ncomment TextureFile a.png\n{}", HEADER);
+        let (header, offset) = parse_header(Path::new("test.ply"), data.as_bytes()).unwrap();
+        assert_eq!(offset, data.len());
+        assert_eq!(header.format, PlyFormat::Ascii);
+        assert_eq!(header.comments, ["TextureFile a.png"]);
+        let counts: Vec<(&str, usize)> = header.elements.iter().map(|e| (e.name.as_str(), e.count)).collect();
+        assert_eq!(counts, [("vertex", 4), ("face", 4)]);
+    }
+}
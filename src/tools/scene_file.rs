@@ -0,0 +1,295 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    engine::{Camera, ColorMode, Engine, LightSource, RenderMode},
+    material::Material,
+    math::vector::Vec3,
+    projection::Projection,
+    scene::{Node, Scene, Transform},
+    texture::TextureFilter,
+};
+
+use super::{camera_path_file::read_camera_path_file, image::read_image_file, logger::Logger, mesh_file::read_mesh_file};
+use crate::log_debug;
+
+/*
+ * Engine settings given by a scene file, unset values keep the engine ones
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderSettings {
+    pub mode: Option<String>,
+    pub color_mode: Option<String>,
+    pub texture_filter: Option<String>,
+    pub threads: Option<usize>,
+    pub face_culling: Option<bool>,
+    // chars from the darkest to the brightest
+    pub shading_ramp: Option<String>,
+}
+
+impl RenderSettings {
+    pub fn apply(&self, engine: &mut Engine) -> std::result::Result<(), String> {
+        if let Some(mode) = &self.mode {
+            engine.render_mode = mode.parse::<RenderMode>()?;
+        }
+        if let Some(color_mode) = &self.color_mode {
+            engine.color_mode = color_mode.parse::<ColorMode>()?;
+        }
+        if let Some(texture_filter) = &self.texture_filter {
+            engine.texture_filter = texture_filter.parse::<TextureFilter>()?;
+        }
+        if let Some(threads) = self.threads {
+            engine.threads = threads.max(1);
+        }
+        if let Some(face_culling) = self.face_culling {
+            engine.face_culling = face_culling;
+        }
+        if let Some(shading_ramp) = &self.shading_ramp {
+            if shading_ramp.is_empty() {
+                return Err("The shading ramp needs at least one char".to_string());
+            }
+            engine.shading_ramp = shading_ramp.chars().collect();
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BackgroundDescription {
+    char: Option<char>,
+    color: Option<[f32; 3]>,
+}
+
+/*
+ * Angles are given in degrees
+ */
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    position: [f32; 3],
+    #[serde(default)]
+    pitch: f32,
+    #[serde(default)]
+    yaw: f32,
+    // "perspective:<fov>" or "orthographic:<view height>"
+    projection: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightDescription {
+    position: [f32; 3],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDescription {
+    diffuse: Option<[f32; 3]>,
+    texture: Option<String>,
+}
+
+/*
+ * Copy of the object mesh placed relatively to the object
+ */
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InstanceDescription {
+    #[serde(default)]
+    position: [f32; 3],
+    #[serde(default)]
+    rotation: [f32; 3],
+    #[serde(default = "default_scale")]
+    scale: [f32; 3],
+    material: Option<MaterialDescription>,
+}
+
+/*
+ * With instances, the object is a group drawing its mesh once per instance
+ */
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectDescription {
+    name: String,
+    mesh: Option<String>,
+    parent: Option<String>,
+    #[serde(default)]
+    position: [f32; 3],
+    #[serde(default)]
+    rotation: [f32; 3],
+    #[serde(default = "default_scale")]
+    scale: [f32; 3],
+    material: Option<MaterialDescription>,
+    #[serde(default)]
+    instances: Vec<InstanceDescription>,
+}
+
+fn default_scale() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    background: Option<BackgroundDescription>,
+    #[serde(default)]
+    render: RenderSettings,
+    camera: Option<CameraDescription>,
+    // TOML file of camera keyframes
+    camera_path: Option<String>,
+    #[serde(default)]
+    lights: Vec<LightDescription>,
+    #[serde(default)]
+    objects: Vec<ObjectDescription>,
+}
+
+/*
+ * Scene loaded from a file, with the settings to apply on the engine
+ */
+#[derive(Debug)]
+pub struct SceneFile {
+    pub scene: Scene,
+    pub render: RenderSettings,
+    pub background: Option<char>,
+    pub background_color: Option<Vec3>,
+}
+
+impl SceneFile {
+    pub fn apply(&self, engine: &mut Engine) -> std::result::Result<(), String> {
+        self.render.apply(engine)?;
+        if let Some(background) = self.background {
+            engine.background = background;
+        }
+        if let Some(background_color) = self.background_color {
+            engine.background_color = background_color;
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn to_vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn to_transform(position: [f32; 3], rotation: [f32; 3], scale: [f32; 3]) -> Transform {
+    Transform {
+        position: to_vec3(position),
+        rotation: to_vec3(rotation.map(f32::to_radians)),
+        scale: to_vec3(scale),
+    }
+}
+
+fn read_material(name: &str, description: &Option<MaterialDescription>, directory: &Path) -> Result<Option<Material>> {
+    let Some(description) = description else {
+        return Ok(None);
+    };
+    let mut material = Material::new(name);
+    if let Some(diffuse) = description.diffuse {
+        material.diffuse = to_vec3(diffuse);
+    }
+    if let Some(texture) = &description.texture {
+        material.texture = Some(Arc::new(read_image_file(&directory.join(texture))?));
+    }
+    Ok(Some(material))
+}
+
+/*
+ * Read a TOML scene file, the paths inside are relative to the file
+ */
+pub fn read_scene_file(path: &Path, logger: &Logger) -> Result<SceneFile> {
+    let content = fs::read_to_string(path)?;
+    let description: SceneDescription = toml::from_str(&content)
+        .map_err(|e| invalid_data(format!("Invalid scene file {:?}: {}", path, e)))?;
+    let directory = path.parent().unwrap_or(Path::new("."));
+
+    let mut scene = Scene::new();
+    let mut meshes: HashMap<String, usize> = HashMap::new();
+    let mut nodes: HashMap<String, usize> = HashMap::new();
+    for object in description.objects {
+        let mesh = match &object.mesh {
+            Some(mesh_path) => Some(match meshes.get(mesh_path) {
+                Some(&index) => index,
+                None => {
+                    log_debug!(logger, "Load mesh {} for {}", mesh_path, object.name);
+                    let mesh = read_mesh_file(&directory.join(mesh_path), logger)?;
+                    let index = scene.add_mesh(mesh);
+                    meshes.insert(mesh_path.clone(), index);
+                    index
+                }
+            }),
+            None => None,
+        };
+        let parent = match &object.parent {
+            Some(parent) => Some(*nodes.get(parent).ok_or_else(|| {
+                invalid_data(format!("The parent '{}' of '{}' must be declared before it", parent, object.name))
+            })?),
+            None => None,
+        };
+        let material = read_material(&object.name, &object.material, directory)?;
+        let transform = to_transform(object.position, object.rotation, object.scale);
+        let is_group = !object.instances.is_empty();
+        if is_group && mesh.is_none() {
+            return Err(invalid_data(format!("The object '{}' has instances but no mesh", object.name)));
+        }
+        let index = scene.add_node(Node {
+            name: object.name.clone(),
+            transform,
+            parent,
+            mesh: if is_group { None } else { mesh },
+            material: if is_group { None } else { material.clone() },
+        });
+        if nodes.insert(object.name.clone(), index).is_some() {
+            return Err(invalid_data(format!("The object name '{}' is used twice", object.name)));
+        }
+        for (i, instance) in object.instances.iter().enumerate() {
+            let name = format!("{}#{}", object.name, i);
+            let instance_material = read_material(&name, &instance.material, directory)?;
+            scene.add_node(Node {
+                name,
+                transform: to_transform(instance.position, instance.rotation, instance.scale),
+                parent: Some(index),
+                mesh,
+                material: instance_material.or_else(|| material.clone()),
+            });
+        }
+    }
+
+    scene.lights = description.lights.iter()
+        .map(|light| LightSource::at(&to_vec3(light.position)))
+        .collect();
+    scene.camera = match description.camera {
+        Some(camera) => {
+            let projection = match &camera.projection {
+                Some(projection) => projection.parse::<Projection>().map_err(invalid_data)?,
+                None => Projection::default(),
+            };
+            Some(Camera::new(to_vec3(camera.position), camera.pitch.to_radians(), camera.yaw.to_radians(), projection))
+        }
+        None => None,
+    };
+
+    scene.camera_path = match &description.camera_path {
+        Some(camera_path) => Some(read_camera_path_file(&directory.join(camera_path))?),
+        None => None,
+    };
+
+    let background = description.background.as_ref();
+    Ok(SceneFile {
+        scene,
+        render: description.render,
+        background: background.and_then(|background| background.char),
+        background_color: background.and_then(|background| background.color).map(to_vec3),
+    })
+}
@@ -0,0 +1,422 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::core::{
+    math::{
+        math::{cross_prod, dot},
+        triangle::Triangle3D,
+        vector::Vec3,
+    },
+    mesh::Mesh,
+};
+
+use super::{logger::Logger, mesh_file::LoadError};
+use crate::{log_debug, log_warn};
+
+// distance under which the vertices of a file become a single one
+pub const DEFAULT_WELD_EPSILON: f32 = 1e-5;
+
+const HEADER_SIZE: usize = 80;
+const FACET_SIZE: usize = 50;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StlEncoding {
+    Binary,
+    Ascii,
+}
+
+/*
+ * Triangle of the file with its normal, zero when the file leaves it to the winding
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct Facet {
+    pub normal: Vec3,
+    pub triangle: Triangle3D,
+}
+
+/*
+ * Read a binary or ASCII STL file, the vertices closer than `DEFAULT_WELD_EPSILON` welded
+ */
+pub fn read_stl_file(path: &Path, logger: &Logger) -> Result<Mesh, LoadError> {
+    read_stl_file_welded(path, DEFAULT_WELD_EPSILON, logger)
+}
+
+/*
+ * Read a STL file, the vertices closer than `epsilon` welded (0 keeps them as they are).
+ * A facet wound against its normal is turned over, the normal giving its front face.
+ * The normals are kept on the triangles for the shading and `write_stl`, a zero one is
+ * left to the winding.
+ */
+pub fn read_stl_file_welded(path: &Path, epsilon: f32, logger: &Logger) -> Result<Mesh, LoadError> {
+    let data = fs::read(path)?;
+    let facets = parse_stl(path, &data)?;
+    let mut flipped = 0;
+    let mut triangles: Vec<Triangle3D> = facets
+        .into_iter()
+        .map(|facet| {
+            let triangle = facet.triangle;
+            let normal = (facet.normal.length2() > 0.0).then(|| facet.normal.normalize());
+            if dot(facet.normal, winding_normal(&triangle)) < 0.0 {
+                flipped += 1;
+                Triangle3D::new(triangle.v1, triangle.v3, triangle.v2).with_normal(normal)
+            } else {
+                triangle.with_normal(normal)
+            }
+        })
+        .collect();
+    if flipped > 0 {
+        log_warn!(logger, "{:?}: {} facets wound against their normal were turned over", path, flipped);
+    }
+    let read = triangles.len();
+    let welded = weld_vertices(&mut triangles, epsilon);
+    log_debug!(
+        logger,
+        "{:?}: {} facets, {} vertices welded, {} degenerate facets removed",
+        path,
+        read,
+        welded,
+        read - triangles.len()
+    );
+    Ok(Mesh::new(triangles))
+}
+
+/*
+ * Facets of a STL file. A file whose size matches the facet count of its header is binary,
+ * even when the header starts with "solid" as some exporters write.
+ */
+pub fn parse_stl(path: &Path, data: &[u8]) -> Result<Vec<Facet>, LoadError> {
+    let binary_size = binary_facet_count(data).map(|count| HEADER_SIZE + 4 + count * FACET_SIZE);
+    if binary_size == Some(data.len()) {
+        return parse_binary(path, data);
+    }
+    if data.trim_ascii_start().starts_with(b"solid") {
+        if let Ok(text) = std::str::from_utf8(data) {
+            return parse_ascii(path, text);
+        }
+    }
+    match binary_size {
+        // trailing bytes after the facets are ignored
+        Some(size) if size <= data.len() => parse_binary(path, data),
+        Some(size) => Err(LoadError::parse(
+            path,
+            None,
+            format!("Truncated binary STL: {} bytes expected, {} found", size, data.len()),
+        )),
+        None => Err(LoadError::parse(path, None, "Neither an ASCII nor a binary STL file")),
+    }
+}
+
+fn binary_facet_count(data: &[u8]) -> Option<usize> {
+    let count = data.get(HEADER_SIZE..HEADER_SIZE + 4)?;
+    Some(u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize)
+}
+
+/*
+ * 80 bytes of header, the facet count, then per facet the normal and the 3 vertices
+ * as little-endian f32 and 2 bytes of attributes
+ */
+fn parse_binary(path: &Path, data: &[u8]) -> Result<Vec<Facet>, LoadError> {
+    let count = binary_facet_count(data).ok_or_else(|| LoadError::parse(path, None, "Missing binary STL header"))?;
+    let read_vec3 = |bytes: &[u8]| {
+        let float = |i: usize| f32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Vec3::new(float(0), float(4), float(8))
+    };
+    Ok(data[HEADER_SIZE + 4..]
+        .chunks_exact(FACET_SIZE)
+        .take(count)
+        .map(|facet| Facet {
+            normal: read_vec3(&facet[0..12]),
+            triangle: Triangle3D::new(read_vec3(&facet[12..24]), read_vec3(&facet[24..36]), read_vec3(&facet[36..48])),
+        })
+        .collect())
+}
+
+/*
+ * "solid", then "facet normal nx ny nz", "outer loop", the vertices, "endloop", "endfacet"... "endsolid".
+ * A loop of more than 3 vertices is split in a fan.
+ */
+fn parse_ascii(path: &Path, text: &str) -> Result<Vec<Facet>, LoadError> {
+    let mut facets = Vec::new();
+    let mut normal: Option<Vec3> = None;
+    let mut corners: Vec<Vec3> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let error = |message: &str| LoadError::parse(path, Some(number), format!("{} in '{}'", message, line.trim()));
+        let parse_vec3 = |values: &[&str]| -> Result<Vec3, LoadError> {
+            match values {
+                [x, y, z] => match (x.parse(), y.parse(), z.parse()) {
+                    (Ok(x), Ok(y), Ok(z)) => Ok(Vec3::new(x, y, z)),
+                    _ => Err(error("Invalid number")),
+                },
+                _ => Err(error("Expected 3 coordinates")),
+            }
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("facet") => {
+                if normal.is_some() {
+                    return Err(error("Facet inside a facet"));
+                }
+                normal = match tokens.get(1) {
+                    Some(&"normal") => Some(parse_vec3(&tokens[2..])?),
+                    _ => return Err(error("Expected 'facet normal'")),
+                };
+                corners.clear();
+            }
+            Some("vertex") => {
+                if normal.is_none() {
+                    return Err(error("Vertex outside a facet"));
+                }
+                corners.push(parse_vec3(&tokens[1..])?);
+            }
+            Some("endfacet") => {
+                let Some(facet_normal) = normal.take() else {
+                    return Err(error("End of a facet never opened"));
+                };
+                if corners.len() < 3 {
+                    return Err(error("Facet of less than 3 vertices"));
+                }
+                for i in 1..corners.len() - 1 {
+                    facets.push(Facet {
+                        normal: facet_normal,
+                        triangle: Triangle3D::new(corners[0], corners[i], corners[i + 1]),
+                    });
+                }
+            }
+            Some("solid" | "endsolid" | "outer" | "endloop") | None => {}
+            Some(_) => return Err(error("Unknown keyword")),
+        }
+    }
+    if normal.is_some() {
+        return Err(LoadError::parse(path, None, "Unterminated facet at the end of the file"));
+    }
+    Ok(facets)
+}
+
+/*
+ * Normal given by the winding of the triangle (counter-clockwise seen from the front)
+ */
+fn winding_normal(triangle: &Triangle3D) -> Vec3 {
+    cross_prod(triangle.v2 - triangle.v1, triangle.v3 - triangle.v1)
+}
+
+/*
+ * Unit normal written for the facet, the one read with it or else the one of its winding,
+ * zero for a degenerate triangle
+ */
+pub fn facet_normal(triangle: &Triangle3D) -> Vec3 {
+    let normal = triangle.normal.unwrap_or_else(|| winding_normal(triangle));
+    if normal.length2() > 0.0 {
+        normal.normalize()
+    } else {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+}
+
+/*
+ * Move each vertex on the first one met within `epsilon`, the facets are stored with their
+ * own copy of each corner, and remove the triangles left with two identical corners.
+ * Returns the number of vertices moved.
+ */
+pub fn weld_vertices(triangles: &mut Vec<Triangle3D>, epsilon: f32) -> usize {
+    if epsilon <= 0.0 {
+        return 0;
+    }
+    // vertices kept, by cell of side epsilon: a match lies in the cell or its neighbours
+    let mut grid: HashMap<(i64, i64, i64), Vec<Vec3>> = HashMap::new();
+    let cell = |v: Vec3| {
+        (
+            (v.x / epsilon).floor() as i64,
+            (v.y / epsilon).floor() as i64,
+            (v.z / epsilon).floor() as i64,
+        )
+    };
+    let mut welded = 0;
+    for triangle in triangles.iter_mut() {
+        for vertex in [&mut triangle.v1, &mut triangle.v2, &mut triangle.v3] {
+            let (x, y, z) = cell(*vertex);
+            let found = (-1..=1)
+                .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (x + dx, y + dy, z + dz))))
+                .filter_map(|key| grid.get(&key))
+                .flatten()
+                .find(|kept| (**kept - *vertex).length2() <= epsilon * epsilon)
+                .copied();
+            match found {
+                Some(kept) => {
+                    if (kept.x, kept.y, kept.z) != (vertex.x, vertex.y, vertex.z) {
+                        welded += 1;
+                    }
+                    *vertex = kept;
+                }
+                None => grid.entry((x, y, z)).or_default().push(*vertex),
+            }
+        }
+    }
+    let same = |a: Vec3, b: Vec3| (a.x, a.y, a.z) == (b.x, b.y, b.z);
+    triangles.retain(|t| !same(t.v1, t.v2) && !same(t.v2, t.v3) && !same(t.v3, t.v1));
+    welded
+}
+
+/*
+ * Write the triangles of the mesh, named after the file. The texture coordinates and
+ * the materials have no place in STL and are left out.
+ */
+pub fn write_stl_file(path: &Path, mesh: &Mesh, encoding: StlEncoding) -> io::Result<()> {
+    let name = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_stl(&mut writer, &name, mesh, encoding)?;
+    writer.flush()
+}
+
+pub fn write_stl<W: Write>(writer: &mut W, name: &str, mesh: &Mesh, encoding: StlEncoding) -> io::Result<()> {
+    match encoding {
+        StlEncoding::Binary => write_binary(writer, name, mesh),
+        StlEncoding::Ascii => write_ascii(writer, name, mesh),
+    }
+}
+
+fn write_binary<W: Write>(writer: &mut W, name: &str, mesh: &Mesh) -> io::Result<()> {
+    let count = u32::try_from(mesh.triangles.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many triangles for a binary STL file"))?;
+    // a header starting with "solid" would pass for ASCII
+    let mut header = [0u8; HEADER_SIZE];
+    let title = format!("engine_3d {}", name);
+    let title = &title.as_bytes()[..title.len().min(HEADER_SIZE)];
+    header[..title.len()].copy_from_slice(title);
+    writer.write_all(&header)?;
+    writer.write_all(&count.to_le_bytes())?;
+    for triangle in &mesh.triangles {
+        for v in [facet_normal(triangle), triangle.v1, triangle.v2, triangle.v3] {
+            for value in [v.x, v.y, v.z] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        writer.write_all(&[0, 0])?;
+    }
+    Ok(())
+}
+
+fn write_ascii<W: Write>(writer: &mut W, name: &str, mesh: &Mesh) -> io::Result<()> {
+    writeln!(writer, "solid {}", name)?;
+    for triangle in &mesh.triangles {
+        let normal = facet_normal(triangle);
+        writeln!(writer, "  facet normal {:e} {:e} {:e}", normal.x, normal.y, normal.z)?;
+        writeln!(writer, "    outer loop")?;
+        for v in [triangle.v1, triangle.v2, triangle.v3] {
+            writeln!(writer, "      vertex {:e} {:e} {:e}", v.x, v.y, v.z)?;
+        }
+        writeln!(writer, "    endloop")?;
+        writeln!(writer, "  endfacet")?;
+    }
+    writeln!(writer, "endsolid {}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::test_files::{corners, read_file};
+
+    fn triangle(v1: (f32, f32, f32), v2: (f32, f32, f32), v3: (f32, f32, f32)) -> Triangle3D {
+        let vec = |(x, y, z): (f32, f32, f32)| Vec3::new(x, y, z);
+        Triangle3D::new(vec(v1), vec(v2), vec(v3))
+    }
+
+    fn square() -> Mesh {
+        Mesh::new(vec![
+            triangle((0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0)),
+            triangle((0.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)),
+        ])
+    }
+
+    #[test]
+    fn ascii_is_detected() {
+        let text = "solid test\n\
+            facet normal 0 0 1\n  outer loop\n\
+            vertex 0 0 0\n vertex 1 0 0\n vertex 1 1 0\n vertex 0 1 0\n\
+            endloop\nendfacet\nendsolid test\n";
+        let facets = parse_stl(Path::new("test.stl"), text.as_bytes()).unwrap();
+        // the quad is split in a fan
+        assert_eq!(facets.len(), 2);
+        assert_eq!(corners(&facets[1].triangle), [(0.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)]);
+        assert_eq!((facets[0].normal.x, facets[0].normal.y, facets[0].normal.z), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn binary_header_starting_with_solid_is_detected() {
+        let mut data = Vec::new();
+        write_stl(&mut data, "test", &square(), StlEncoding::Binary).unwrap();
+        data[..5].copy_from_slice(b"solid");
+        let facets = parse_stl(Path::new("test.stl"), &data).unwrap();
+        assert_eq!(facets.len(), 2);
+    }
+
+    #[test]
+    fn truncated_binary_is_rejected() {
+        let mut data = Vec::new();
+        write_stl(&mut data, "test", &square(), StlEncoding::Binary).unwrap();
+        data.truncate(data.len() - 1);
+        assert!(parse_stl(Path::new("test.stl"), &data).is_err());
+    }
+
+    #[test]
+    fn close_vertices_are_welded() {
+        let mut triangles = vec![
+            triangle((0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0)),
+            triangle((1e-7, 0.0, 0.0), (1.0, 1.0, 1e-7), (0.0, 1.0, 0.0)),
+            // collapses once welded
+            triangle((0.0, 1.0, 0.0), (0.0, 1.0, 1e-7), (5.0, 5.0, 5.0)),
+        ];
+        assert_eq!(weld_vertices(&mut triangles, 1e-5), 3);
+        assert_eq!(triangles.len(), 2);
+        assert_eq!(corners(&triangles[1]), [(0.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)]);
+    }
+
+    #[test]
+    fn written_facets_are_read_back() {
+        let mesh = square();
+        for encoding in [StlEncoding::Binary, StlEncoding::Ascii] {
+            let mut data = Vec::new();
+            write_stl(&mut data, "square", &mesh, encoding).unwrap();
+            assert_eq!(data.starts_with(b"solid"), encoding == StlEncoding::Ascii);
+            let facets = parse_stl(Path::new("square.stl"), &data).unwrap();
+            assert_eq!(facets.len(), mesh.triangles.len());
+            for (facet, triangle) in facets.iter().zip(&mesh.triangles) {
+                assert_eq!(corners(&facet.triangle), corners(triangle));
+                assert_eq!((facet.normal.x, facet.normal.y, facet.normal.z), (0.0, 0.0, 1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn facet_normals_are_kept() {
+        let text = "solid test\n\
+            facet normal 1 0 1\n outer loop\n vertex 0 0 0\n vertex 1 0 0\n vertex 1 1 0\n endloop\n endfacet\n\
+            facet normal 0 0 -2\n outer loop\n vertex 0 0 0\n vertex 1 1 0\n vertex 0 1 0\n endloop\n endfacet\n\
+            facet normal 0 0 0\n outer loop\n vertex 0 0 1\n vertex 1 0 1\n vertex 1 1 1\n endloop\n endfacet\n\
+            endsolid test\n";
+        let mesh = read_file("stl_normals", "test.stl", text.as_bytes(), |path, logger| {
+            read_stl_file_welded(path, 0.0, logger)
+        })
+        .unwrap();
+
+        let normal = |v: Vec3| (v.x, v.y, v.z);
+        let tilted = Vec3::new(1.0, 0.0, 1.0).normalize();
+        assert_eq!(mesh.triangles[0].normal.map(normal), Some(normal(tilted)));
+        // turned over, the normal kept
+        assert_eq!(corners(&mesh.triangles[1]), [(0.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 1.0, 0.0)]);
+        assert_eq!(mesh.triangles[1].normal.map(normal), Some((0.0, 0.0, -1.0)));
+        assert_eq!(mesh.triangles[2].normal.map(normal), None);
+
+        let mut data = Vec::new();
+        write_stl(&mut data, "test", &mesh, StlEncoding::Ascii).unwrap();
+        let facets = parse_stl(Path::new("test.stl"), &data).unwrap();
+        let expected = [tilted, Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0)];
+        for (facet, expected) in facets.iter().zip(expected) {
+            assert!((facet.normal - expected).length() < 1e-6, "{:?} written for {:?}", facet.normal, expected);
+        }
+    }
+}
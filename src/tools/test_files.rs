@@ -0,0 +1,32 @@
+use std::{fs, path::Path};
+
+use crate::core::math::triangle::Triangle3D;
+
+use super::logger::Logger;
+
+/*
+ * Run `f` in a temporary directory named after the test, with a logger writing in it.
+ * The directory is removed after.
+ */
+pub fn with_temp_dir<T>(name: &str, f: impl FnOnce(&Path, &Logger) -> T) -> T {
+    let dir = std::env::temp_dir().join(format!("engine_3d_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let result = f(&dir, &Logger::from(&dir.join("test.log")));
+    fs::remove_dir_all(&dir).unwrap();
+    result
+}
+
+/*
+ * Write the data to `file_name` in a temporary directory and read it back with `read`
+ */
+pub fn read_file<T>(name: &str, file_name: &str, data: &[u8], read: impl FnOnce(&Path, &Logger) -> T) -> T {
+    with_temp_dir(name, |dir, logger| {
+        let path = dir.join(file_name);
+        fs::write(&path, data).unwrap();
+        read(&path, logger)
+    })
+}
+
+pub fn corners(triangle: &Triangle3D) -> [(f32, f32, f32); 3] {
+    [triangle.v1, triangle.v2, triangle.v3].map(|v| (v.x, v.y, v.z))
+}
@@ -1,100 +1,496 @@
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     fs::File,
-    io::{BufRead, BufReader, Result},
-    path::Path, usize,
+    io::{BufRead, BufReader, BufWriter, Result, Write},
+    path::Path,
+    sync::Arc,
 };
 
-use crate::core::math::{
-    triangle::Triangle3D,
-    vector::Vec3,
+use crate::core::{
+    material::Material,
+    math::{
+        triangle::Triangle3D,
+        vector::{Vec2, Vec3},
+    },
+    mesh::{Mesh, Point},
+    texture::{Texture, TextureWrap},
 };
 
-use super::logger::Logger;
+use super::{
+    image::{read_image_file, write_png},
+    logger::Logger,
+    mesh_file::LoadError,
+    stl::facet_normal,
+};
+use crate::{log_trace, log_warn};
+
+/*
+ * Index of an OBJ face element, 1-based or negative (relative to the end of the list)
+ */
+fn parse_index(index: &str, len: usize) -> std::result::Result<usize, String> {
+    let error = || format!("Erreur de parsing pour l'indice '{}'", index);
+    let index: isize = index.parse().map_err(|_| error())?;
+    let resolved = if index < 0 { len as isize + index } else { index - 1 };
+    if 0 <= resolved && (resolved as usize) < len {
+        Ok(resolved as usize)
+    } else {
+        Err(error())
+    }
+}
 
-pub fn read_object_file(path: &Path, logger: &Logger) -> Result<Vec<Triangle3D>> {
+pub fn read_object_file(path: &Path, logger: &Logger) -> std::result::Result<Mesh, LoadError> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
     let mut vertices = Vec::new();
-    let mut faces = Vec::new();
-    for line in reader.lines() {
-        let line = line.expect("Error to load line");
+    let mut uvs = Vec::new();
+    let mut materials: Vec<Material> = Vec::new();
+    let mut material_indices: HashMap<String, usize> = HashMap::new();
+    let mut current_material: Option<usize> = None;
+    let mut triangles = Vec::new();
+    let mut points = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
         let line = line.trim();
+        let error = |message: &str| LoadError::parse(path, Some(index + 1), format!("{} '{}'", message, line));
         if line.is_empty() {
             continue;
         }
 
         let tokens: Vec<&str> = line.split_whitespace().collect();
 
-        match tokens.get(0) {
+        match tokens.first() {
             Some(&"v") => {
-                logger.log(format!("vextex: {}", line));
-                if let [_, x, y, z] = &tokens[..] {
-                    let vertex = Vec3 {
-                        x: x.parse()
-                            .expect(&format!("Erreur de parsing pour le vertex '{}'", line)),
-                        y: y.parse()
-                            .expect(&format!("Erreur de parsing pour le vertex '{}'", line)),
-                        z: z.parse()
-                            .expect(&format!("Erreur de parsing pour le vertex '{}'", line)),
+                log_trace!(logger, "vertex: {}", line);
+                if let [_, x, y, z, ..] = &tokens[..] {
+                    let vertex = match (x.parse(), y.parse(), z.parse()) {
+                        (Ok(x), Ok(y), Ok(z)) => Vec3 { x, y, z },
+                        _ => return Err(error("Erreur de parsing pour le vertex")),
                     };
                     vertices.push(vertex);
                 }
             }
+            Some(&"vt") => {
+                log_trace!(logger, "texture coordinate: {}", line);
+                if let [_, u, rest @ ..] = &tokens[..] {
+                    let v = rest.first().unwrap_or(&"0");
+                    let uv = match (u.parse(), v.parse()) {
+                        (Ok(x), Ok(y)) => Vec2 { x, y },
+                        _ => return Err(error("Erreur de parsing pour la coordonnée de texture")),
+                    };
+                    uvs.push(uv);
+                }
+            }
             Some(&"f") => {
-                logger.log(format!("face: {}", line));
-                let face: std::result::Result<Vec<usize>, String> = tokens[1..]
+                log_trace!(logger, "face: {}", line);
+                // each element is "v", "v/vt", "v//vn" or "v/vt/vn"
+                let face: std::result::Result<Vec<(usize, Option<usize>)>, String> = tokens[1..]
                     .iter()
-                    .fold(Ok(vec![]), |acc, &index| {
-                      let parsed_index = index.parse::<usize>().map(|i| i - 1)
-                        .map_err(|_| format!("Erreur de parsing pour l'indice '{}'", index));
-                      match acc {
-                          Ok(mut vec) => {
-                              match parsed_index {
-                                  Ok(val) => {
-                                      vec.push(val);
-                                      Ok(vec)
-                                  },
-                                  Err(e) => Err(e),
-                              }
-                          },
-                          Err(e) => Err(e), // Si l'accumulateur est déjà une erreur, on la propage
-                      }
-                    });
+                    .map(|&element| {
+                        let mut indices = element.split('/');
+                        let vertex = parse_index(indices.next().unwrap_or_default(), vertices.len())?;
+                        let uv = match indices.next() {
+                            Some(index) if !index.is_empty() => Some(parse_index(index, uvs.len())?),
+                            _ => None,
+                        };
+                        Ok((vertex, uv))
+                    })
+                    .collect();
 
                 match face {
-                    Ok(face_indices) => faces.push(face_indices),
-                    _ => ()
+                    Ok(face) if face.len() >= 3 => {
+                        // fan triangulation, quads give the same split as before
+                        for i in 1..face.len() - 1 {
+                            let corners = [face[0], face[i], face[i + 1]];
+                            let mut triangle = Triangle3D::new(
+                                vertices[corners[0].0],
+                                vertices[corners[1].0],
+                                vertices[corners[2].0],
+                            ).with_material(current_material);
+                            if let [(_, Some(uv1)), (_, Some(uv2)), (_, Some(uv3))] = corners {
+                                triangle = triangle.with_uv([uvs[uv1], uvs[uv2], uvs[uv3]]);
+                            }
+                            triangles.push(triangle);
+                        }
+                    }
+                    Ok(_) => (),
+                    Err(e) => log_warn!(logger, "{}", e),
+                }
+            }
+            Some(&"p") => {
+                for element in &tokens[1..] {
+                    match parse_index(element, vertices.len()) {
+                        Ok(vertex) => points.push(Point::new(vertices[vertex])),
+                        Err(e) => log_warn!(logger, "{}", e),
+                    }
                 }
             }
+            Some(&"mtllib") => {
+                let library_path = path.with_file_name(tokens[1..].join(" "));
+                match read_material_file(&library_path, logger) {
+                    Ok(library) => {
+                        for material in library {
+                            material_indices.insert(material.name.clone(), materials.len());
+                            materials.push(material);
+                        }
+                    }
+                    Err(e) => log_warn!(logger, "Unable to read the material library {:?}: {}", library_path, e),
+                }
+            }
+            Some(&"usemtl") => {
+                current_material = tokens.get(1).and_then(|name| material_indices.get(*name).copied());
+            }
             _ => {}
         }
     }
 
-    let mut triangles = Vec::new();
-    for face in faces {
-        match face.len() {
-            3 => {
-                triangles.push(Triangle3D {
-                    v1: vertices[face[0]].clone(),
-                    v2: vertices[face[1]].clone(),
-                    v3: vertices[face[2]].clone(),
-                });
+    Ok(Mesh {
+        triangles,
+        points,
+        materials,
+    })
+}
+
+/*
+ * Read a MTL file, only the diffuse colour (Kd) and texture (map_Kd) are used
+ */
+pub fn read_material_file(path: &Path, logger: &Logger) -> Result<Vec<Material>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut materials: Vec<Material> = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        match (tokens.first(), materials.last_mut()) {
+            (Some(&"newmtl"), _) => {
+                materials.push(Material::new(&tokens[1..].join(" ")));
+            }
+            (Some(&"Kd"), Some(material)) => {
+                if let [_, r, g, b] = &tokens[..] {
+                    if let (Ok(r), Ok(g), Ok(b)) = (r.parse(), g.parse(), b.parse()) {
+                        material.diffuse = Vec3::new(r, g, b);
+                    }
+                }
             }
-            4 => {
-                triangles.push(Triangle3D {
-                    v1: vertices[face[0]].clone(),
-                    v2: vertices[face[1]].clone(),
-                    v3: vertices[face[2]].clone(),
-                });
-                triangles.push(Triangle3D {
-                    v1: vertices[face[2]].clone(),
-                    v2: vertices[face[3]].clone(),
-                    v3: vertices[face[0]].clone(),
-                });
+            (Some(&"map_Kd"), Some(material)) => {
+                let (texture_path, wrap) = parse_texture_map(&tokens[1..]);
+                let texture_path = path.with_file_name(texture_path);
+                match read_image_file(&texture_path) {
+                    Ok(mut texture) => {
+                        texture.wrap = wrap;
+                        material.texture = Some(Arc::new(texture));
+                    }
+                    Err(e) => log_warn!(logger, "Unable to read the texture {:?}: {}", texture_path, e),
+                }
             }
             _ => {}
         }
     }
+    Ok(materials)
+}
 
-    Ok(triangles)
+/*
+ * Split the options of a texture map statement from its path (which may contain spaces)
+ */
+fn parse_texture_map(tokens: &[&str]) -> (String, TextureWrap) {
+    let mut wrap = TextureWrap::Repeat;
+    let mut i = 0;
+    while i < tokens.len() && tokens[i].starts_with('-') {
+        let option = tokens[i];
+        i += 1;
+        match option {
+            "-clamp" => {
+                if tokens.get(i) == Some(&"on") {
+                    wrap = TextureWrap::Clamp;
+                }
+                i += 1;
+            }
+            "-mm" => i += 2,
+            "-o" | "-s" | "-t" => {
+                let mut consumed = 0;
+                while consumed < 3 && i < tokens.len() && tokens[i].parse::<f32>().is_ok() {
+                    i += 1;
+                    consumed += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    (tokens[i.min(tokens.len())..].join(" "), wrap)
+}
+
+/*
+ * Write the meshes as the objects of an OBJ file, their materials in a MTL file of the same name
+ * and their textures in PNG files next to it. The vertices, texture coordinates and normals (one
+ * per face) are written once each, the points as `p` elements. The vertex colours have no place
+ * in OBJ and are left out.
+ */
+pub fn write_object_file(path: &Path, objects: &[(&str, &Mesh)]) -> Result<()> {
+    let stem = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    let library = MaterialLibrary::new(objects);
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "# engine_3d")?;
+    if !library.materials.is_empty() {
+        let library_name = format!("{}.mtl", stem);
+        write_material_file(&path.with_file_name(&library_name), &stem, &library.materials)?;
+        writeln!(writer, "mtllib {}", library_name)?;
+    }
+    let mut vertices = ElementIndex::new("v");
+    let mut uvs = ElementIndex::new("vt");
+    let mut normals = ElementIndex::new("vn");
+    // the material stays in use from one object to the next
+    let mut current_material = None;
+    for (object, (name, mesh)) in objects.iter().enumerate() {
+        let name = element_name(name, "object");
+        writeln!(writer, "o {}", name)?;
+        // the new elements of the object come first, then its faces
+        let mut faces: Vec<u8> = Vec::new();
+        for triangle in &mesh.triangles {
+            let material = library.material(object, triangle.material);
+            if material != current_material {
+                if let Some(material) = material {
+                    let material_name = &library.materials[material].0;
+                    writeln!(faces, "g {}_{}", name, material_name)?;
+                    writeln!(faces, "usemtl {}", material_name)?;
+                }
+                current_material = material;
+            }
+            let normal = facet_normal(triangle);
+            let normal = if normal.length2() > 0.0 {
+                Some(normals.index([normal.x, normal.y, normal.z])?)
+            } else {
+                None
+            };
+            write!(faces, "f")?;
+            for (i, v) in [triangle.v1, triangle.v2, triangle.v3].into_iter().enumerate() {
+                let vertex = vertices.index([v.x, v.y, v.z])?;
+                let uv = match triangle.uv {
+                    Some(uv) => Some(uvs.index([uv[i].x, uv[i].y])?),
+                    None => None,
+                };
+                match (uv, normal) {
+                    (Some(uv), Some(normal)) => write!(faces, " {}/{}/{}", vertex, uv, normal)?,
+                    (Some(uv), None) => write!(faces, " {}/{}", vertex, uv)?,
+                    (None, Some(normal)) => write!(faces, " {}//{}", vertex, normal)?,
+                    (None, None) => write!(faces, " {}", vertex)?,
+                }
+            }
+            writeln!(faces)?;
+        }
+        for point in &mesh.points {
+            let v = point.position;
+            let vertex = vertices.index([v.x, v.y, v.z])?;
+            writeln!(faces, "p {}", vertex)?;
+        }
+        vertices.flush(&mut writer)?;
+        uvs.flush(&mut writer)?;
+        normals.flush(&mut writer)?;
+        writer.write_all(&faces)?;
+    }
+    writer.flush()
+}
+
+/*
+ * 1-based index of each distinct element of a kind, the new ones kept until flushed
+ */
+struct ElementIndex<const N: usize> {
+    keyword: &'static str,
+    indices: HashMap<[u32; N], usize>,
+    lines: Vec<u8>,
+}
+
+impl<const N: usize> ElementIndex<N> {
+    fn new(keyword: &'static str) -> ElementIndex<N> {
+        ElementIndex {
+            keyword,
+            indices: HashMap::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    fn index(&mut self, values: [f32; N]) -> Result<usize> {
+        // -0.0 and 0.0 are the same element
+        let values = values.map(|value| value + 0.0);
+        let key = values.map(f32::to_bits);
+        if let Some(&index) = self.indices.get(&key) {
+            return Ok(index);
+        }
+        write!(self.lines, "{}", self.keyword)?;
+        for value in values {
+            write!(self.lines, " {}", value)?;
+        }
+        writeln!(self.lines)?;
+        let index = self.indices.len() + 1;
+        self.indices.insert(key, index);
+        Ok(index)
+    }
+
+    fn flush<W: Write>(&mut self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.lines)?;
+        self.lines.clear();
+        Ok(())
+    }
+}
+
+/*
+ * Materials of all the objects under unique names, the identical ones written once
+ */
+struct MaterialLibrary<'a> {
+    materials: Vec<(String, Cow<'a, Material>)>,
+    // index in `materials` of each material of each object
+    indices: Vec<Vec<usize>>,
+    // given to the triangles without material when others have one
+    default: Option<usize>,
+}
+
+impl<'a> MaterialLibrary<'a> {
+    fn new(objects: &[(&str, &'a Mesh)]) -> MaterialLibrary<'a> {
+        let mut library = MaterialLibrary {
+            materials: Vec::new(),
+            indices: Vec::new(),
+            default: None,
+        };
+        for (_, mesh) in objects {
+            let indices = mesh
+                .materials
+                .iter()
+                .map(|material| {
+                    match library.materials.iter().position(|(_, kept)| same_material(kept, material)) {
+                        Some(index) => index,
+                        None => library.add(Cow::Borrowed(material)),
+                    }
+                })
+                .collect();
+            library.indices.push(indices);
+        }
+        let unassigned = objects.iter().enumerate().any(|(object, (_, mesh))| {
+            mesh.triangles.iter().any(|triangle| library.material(object, triangle.material).is_none())
+        });
+        if unassigned && !library.materials.is_empty() {
+            library.default = Some(library.add(Cow::Owned(Material::new("default"))));
+        }
+        library
+    }
+
+    fn add(&mut self, material: Cow<'a, Material>) -> usize {
+        let base = element_name(&material.name, "material");
+        let name = (1..)
+            .map(|n| if n == 1 { base.clone() } else { format!("{}_{}", base, n) })
+            .find(|name| self.materials.iter().all(|(kept, _)| kept != name))
+            .unwrap_or(base);
+        self.materials.push((name, material));
+        self.materials.len() - 1
+    }
+
+    fn material(&self, object: usize, index: Option<usize>) -> Option<usize> {
+        index.and_then(|index| self.indices[object].get(index).copied()).or(self.default)
+    }
+}
+
+fn same_material(a: &Material, b: &Material) -> bool {
+    let texture = |material: &Material| material.texture.as_ref().map(Arc::as_ptr);
+    a.name == b.name
+        && (a.diffuse.x, a.diffuse.y, a.diffuse.z) == (b.diffuse.x, b.diffuse.y, b.diffuse.z)
+        && texture(a) == texture(b)
+}
+
+/*
+ * Name without spaces, which end the names of the OBJ statements, nor '#' starting a comment
+ */
+fn element_name(name: &str, fallback: &str) -> String {
+    let name = name.replace('#', "_").split_whitespace().collect::<Vec<_>>().join("_");
+    if name.is_empty() {
+        String::from(fallback)
+    } else {
+        name
+    }
+}
+
+/*
+ * MTL file of the diffuse colours, the textures written in PNG files named after the OBJ one
+ */
+fn write_material_file(path: &Path, stem: &str, materials: &[(String, Cow<Material>)]) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "# engine_3d")?;
+    let mut textures: HashMap<*const Texture, String> = HashMap::new();
+    for (name, material) in materials {
+        writeln!(writer, "newmtl {}", name)?;
+        let Vec3 { x, y, z } = material.diffuse;
+        writeln!(writer, "Kd {} {} {}", x, y, z)?;
+        if let Some(texture) = &material.texture {
+            let file_name = match textures.get(&Arc::as_ptr(texture)) {
+                Some(file_name) => file_name.clone(),
+                None => {
+                    let file_name = element_name(&format!("{}_{}.png", stem, name), "texture.png");
+                    write_png(&path.with_file_name(&file_name), texture)?;
+                    textures.insert(Arc::as_ptr(texture), file_name.clone());
+                    file_name
+                }
+            };
+            match texture.wrap {
+                TextureWrap::Clamp => writeln!(writer, "map_Kd -clamp on {}", file_name)?,
+                TextureWrap::Repeat => writeln!(writer, "map_Kd {}", file_name)?,
+            }
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::test_files::{corners, with_temp_dir};
+
+    fn textured_mesh() -> Mesh {
+        let mut red = Material::new("red");
+        red.diffuse = Vec3::new(1.0, 0.0, 0.0);
+        let mut checker = Material::new("checker");
+        let (black, white) = (Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        let mut texture = Texture::new(2, 2, vec![black, white, white, black]);
+        texture.wrap = TextureWrap::Clamp;
+        checker.texture = Some(Arc::new(texture));
+        let (a, b, c, d) =
+            (Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.5));
+        let mut mesh = Mesh::new(vec![
+            Triangle3D::new(a, b, c)
+                .with_uv([Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)])
+                .with_material(Some(0)),
+            Triangle3D::new(a, c, d)
+                .with_uv([Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, 0.25)])
+                .with_material(Some(1)),
+        ]);
+        mesh.materials = vec![red, checker];
+        mesh
+    }
+
+    #[test]
+    fn written_mesh_is_read_back() {
+        let mesh = textured_mesh();
+        let read = with_temp_dir("wavefront", |dir, logger| {
+            let path = dir.join("quad.obj");
+            write_object_file(&path, &[("quad", &mesh)]).unwrap();
+            read_object_file(&path, logger)
+        })
+        .unwrap();
+
+        assert_eq!(read.triangles.len(), mesh.triangles.len());
+        let uv = |t: &Triangle3D| t.uv.map(|uv| uv.map(|uv| (uv.x, uv.y)));
+        let material = |mesh: &Mesh, t: &Triangle3D| t.material.map(|index| mesh.materials[index].name.clone());
+        for (read_triangle, triangle) in read.triangles.iter().zip(&mesh.triangles) {
+            assert_eq!(corners(read_triangle), corners(triangle));
+            assert_eq!(uv(read_triangle), uv(triangle));
+            assert_eq!(material(&read, read_triangle), material(&mesh, triangle));
+        }
+        let red = &read.materials[read.triangles[0].material.unwrap()];
+        assert_eq!((red.diffuse.x, red.diffuse.y, red.diffuse.z), (1.0, 0.0, 0.0));
+        assert!(red.texture.is_none());
+        let checker = &read.materials[read.triangles[1].material.unwrap()];
+        let texture = checker.texture.as_ref().expect("the texture is read back");
+        assert_eq!((texture.width, texture.height, texture.wrap), (2, 2, TextureWrap::Clamp));
+        let pixels: Vec<_> = texture.pixels.iter().map(|p| (p.x, p.y, p.z)).collect();
+        assert_eq!(pixels, [(0.0, 0.0, 0.0), (1.0, 1.0, 1.0), (1.0, 1.0, 1.0), (0.0, 0.0, 0.0)]);
+    }
 }